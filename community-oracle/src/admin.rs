@@ -0,0 +1,166 @@
+//! Authorization of the requests to the admin APIs of the Community Oracle and the Service Provider.
+//!
+//! Admin API (e.g. eligibility rules moderation) is meant for the operators rather than the Service Provider,
+//! so it's served on a separate bind address and doesn't accept the secret shared with the Service Provider.
+//! Instead, every request to it must contain the `X-Api-Key` header with one of the configured API keys.
+//! Each key has a role, which determines the set of operations available to its holder.
+//!
+//! Service Provider uses the same extractor for its own admin API, so both services follow the same rules.
+
+use crate::responses::ErrorResponse;
+use actix_web::{dev::Payload, error::InternalError, web, FromRequest, HttpRequest, HttpResponse};
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use std::{collections::HashMap, future::Future, pin::Pin, str::FromStr};
+use thiserror::Error;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Role of the API key holder. Every next role includes the permissions of the previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    /// Can only inspect the state of the application.
+    Viewer,
+    /// Can modify the state of the application (e.g. declare communities or ban users).
+    Operator,
+}
+
+impl FromStr for AdminRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Self::Viewer),
+            "operator" => Ok(Self::Operator),
+            _ => Err(format!("Unknown admin role: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdminApiKey {
+    pub key: String,
+    pub role: AdminRole,
+}
+
+impl FromStr for AdminApiKey {
+    type Err = String;
+
+    /// Parses the key in the `key:role` form.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let key = parts.next().unwrap_or_default().trim();
+        let role = parts
+            .next()
+            .ok_or_else(|| "API key must be in the `key:role` form".to_string())?
+            .trim()
+            .parse()?;
+
+        if key.is_empty() {
+            return Err("API key must not be empty".into());
+        }
+
+        Ok(Self {
+            key: key.into(),
+            role,
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AdminAuthError {
+    #[error("Missing or malformed {} header", API_KEY_HEADER)]
+    MissingKey,
+    #[error("Unknown API key")]
+    UnknownKey,
+    #[error("Operation requires the {0:?} role")]
+    InsufficientRole(AdminRole),
+}
+
+/// Storage of the API keys allowed to access the admin API.
+#[derive(Debug, Clone)]
+pub struct AdminAuthenticator {
+    keys: HashMap<String, AdminRole>,
+}
+
+impl AdminAuthenticator {
+    pub fn new(keys: Vec<AdminApiKey>) -> Self {
+        if keys.is_empty() {
+            log::warn!("No admin API keys are configured, admin API is inaccessible");
+        }
+
+        Self {
+            keys: keys.into_iter().map(|key| (key.key, key.role)).collect(),
+        }
+    }
+
+    /// Returns the role of the request sender.
+    pub fn role(&self, req: &HttpRequest) -> Result<AdminRole, AdminAuthError> {
+        let key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(AdminAuthError::MissingKey)?;
+
+        self.keys
+            .get(key)
+            .copied()
+            .ok_or(AdminAuthError::UnknownKey)
+    }
+}
+
+/// Extractor for the admin API requests: checks the API key and decodes the JSON body of the request.
+/// Role of the sender must be checked with `Admin::authorize` before the request is processed.
+///
+/// Requires `AdminAuthenticator` to be registered as the application data.
+#[derive(Debug)]
+pub struct Admin<T> {
+    role: AdminRole,
+    request: T,
+}
+
+impl<T> Admin<T> {
+    /// Returns the request without checking the sender role.
+    /// Useful when the required role depends on the request content.
+    pub fn request(&self) -> &T {
+        &self.request
+    }
+
+    /// Returns the request if its sender has the required role, and the `403 Forbidden` response otherwise.
+    pub fn authorize(self, required: AdminRole) -> Result<T, HttpResponse> {
+        if self.role >= required {
+            Ok(self.request)
+        } else {
+            let error = AdminAuthError::InsufficientRole(required);
+            log::warn!("Rejected admin request: {}", error);
+            Err(HttpResponse::Forbidden().json(ErrorResponse::error(&error.to_string())))
+        }
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Admin<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authenticator = req
+            .app_data::<web::Data<AdminAuthenticator>>()
+            .expect("AdminAuthenticator is not registered");
+        let role = authenticator.role(req);
+        let request = web::Json::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let role = role.map_err(|error| {
+                log::warn!("Rejected unauthenticated admin request: {}", error);
+                let response =
+                    HttpResponse::Unauthorized().json(ErrorResponse::error(&error.to_string()));
+                actix_web::Error::from(InternalError::from_response(error, response))
+            })?;
+            let request = request.await?.into_inner();
+
+            Ok(Admin { role, request })
+        })
+    }
+}
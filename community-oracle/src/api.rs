@@ -0,0 +1,231 @@
+//! HTTP API of the Community Oracle.
+//!
+//! Handlers only extract the authenticated requests and convert the results of the
+//! `CommunityOracle` methods into HTTP responses.
+//!
+//! API is split into two scopes: the main one is used by the Service Provider (see the `auth` module),
+//! and the admin one is used by the oracle operators (see the `admin` module) and is served separately.
+
+use crate::{
+    admin::{Admin, AdminRole},
+    auth::Authenticated,
+    community_oracle::CommunityOracle,
+    error::RequestError,
+    requests::{
+        AllowlistModeRequest, BatchMintingSignatureRequest, ClaimStatusRequest,
+        CommunityUserRequest, CustodialMintRequest, DeclareCommunityRequest,
+        EligibilityRulesRequest, GrantedTokensRequest, MinKarmaRequest, MintingSignatureRequest,
+        MintingSignatureVerificationRequest, RegisterSigningKeyRequest, RelatedCommunitiesRequest,
+        RemoveCommunityRequest, UserKarmaRequest,
+    },
+};
+use actix_web::{web, HttpResponse, Responder, Scope};
+use serde::Serialize;
+
+/// Converts the result of the request processing into `HttpResponse`.
+fn response<T: Serialize>(result: Result<T, RequestError>) -> HttpResponse {
+    match result {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(RequestError::Rejected(error)) => HttpResponse::BadRequest().json(error),
+        Err(RequestError::Internal(error)) => HttpResponse::InternalServerError().json(error),
+    }
+}
+
+async fn tokens_for_user(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<GrantedTokensRequest>,
+) -> impl Responder {
+    response(oracle.tokens_for_user(request.into_inner()).await)
+}
+
+async fn sign_minting_tx(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<MintingSignatureRequest>,
+) -> impl Responder {
+    response(oracle.sign_minting_tx(request.into_inner()).await)
+}
+
+async fn sign_minting_txs_batch(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<BatchMintingSignatureRequest>,
+) -> impl Responder {
+    response(oracle.sign_minting_txs_batch(request.into_inner()).await)
+}
+
+async fn verify_minting_signature(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<MintingSignatureVerificationRequest>,
+) -> impl Responder {
+    response(oracle.verify_minting_signature(request.into_inner()).await)
+}
+
+async fn claim_status(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<ClaimStatusRequest>,
+) -> impl Responder {
+    response(oracle.claim_status(request.into_inner()).await)
+}
+
+async fn register_signing_key(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<RegisterSigningKeyRequest>,
+) -> impl Responder {
+    response(oracle.register_signing_key(request.into_inner()).await)
+}
+
+async fn custodial_mint(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<CustodialMintRequest>,
+) -> impl Responder {
+    response(oracle.custodial_mint(request.into_inner()).await)
+}
+
+async fn related_communities(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<RelatedCommunitiesRequest>,
+) -> impl Responder {
+    response(oracle.related_communities(request.into_inner()).await)
+}
+
+async fn genesis_wallet_address(
+    oracle: web::Data<CommunityOracle>,
+    _request: Authenticated<()>,
+) -> impl Responder {
+    response(oracle.genesis_wallet_address().await)
+}
+
+async fn eligibility_rules(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<EligibilityRulesRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Viewer) {
+        Ok(request) => response(oracle.eligibility_rules(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn ban_user(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<CommunityUserRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.ban_user(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn unban_user(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<CommunityUserRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.unban_user(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn set_allowlist_mode(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<AllowlistModeRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.set_allowlist_mode(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn allow_user(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<CommunityUserRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.allow_user(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn disallow_user(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<CommunityUserRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.disallow_user(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn set_min_karma(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<MinKarmaRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.set_min_karma(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn set_user_karma(
+    oracle: web::Data<CommunityOracle>,
+    request: Admin<UserKarmaRequest>,
+) -> impl Responder {
+    match request.authorize(AdminRole::Operator) {
+        Ok(request) => response(oracle.set_user_karma(request).await),
+        Err(forbidden) => forbidden,
+    }
+}
+
+async fn declare_community(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<DeclareCommunityRequest>,
+) -> impl Responder {
+    response(oracle.declare_community(request.into_inner()).await)
+}
+
+async fn remove_community(
+    oracle: web::Data<CommunityOracle>,
+    request: Authenticated<RemoveCommunityRequest>,
+) -> impl Responder {
+    response(oracle.remove_community(request.into_inner()).await)
+}
+
+async fn communities(
+    oracle: web::Data<CommunityOracle>,
+    _request: Authenticated<()>,
+) -> impl Responder {
+    response(oracle.communities().await)
+}
+
+impl CommunityOracle {
+    pub fn into_web_scope(self) -> Scope {
+        web::scope("api/v0.1/")
+            .data(self.authenticator.clone())
+            .data(self)
+            .service(web::resource("/granted_tokens").to(tokens_for_user))
+            .service(web::resource("/get_minting_signature").to(sign_minting_tx))
+            .service(web::resource("/get_minting_signatures_batch").to(sign_minting_txs_batch))
+            .service(web::resource("/verify_minting_signature").to(verify_minting_signature))
+            .service(web::resource("/claim_status").to(claim_status))
+            .service(web::resource("/custodial/register_signing_key").to(register_signing_key))
+            .service(web::resource("/custodial/mint").to(custodial_mint))
+            .service(web::resource("/related_communities").to(related_communities))
+            .service(web::resource("/genesis_wallet_address").to(genesis_wallet_address))
+            // Community registry mirrors the one of the Service Provider, so it's managed by the Service Provider only.
+            .service(web::resource("/registry/declare_community").to(declare_community))
+            .service(web::resource("/registry/remove_community").to(remove_community))
+            .service(web::resource("/registry/communities").to(communities))
+    }
+
+    pub fn into_admin_scope(self) -> Scope {
+        web::scope("admin/v0.1/")
+            .data(self.admin_authenticator.clone())
+            .data(self)
+            .service(web::resource("/eligibility_rules").to(eligibility_rules))
+            .service(web::resource("/ban_user").to(ban_user))
+            .service(web::resource("/unban_user").to(unban_user))
+            .service(web::resource("/set_allowlist_mode").to(set_allowlist_mode))
+            .service(web::resource("/allow_user").to(allow_user))
+            .service(web::resource("/disallow_user").to(disallow_user))
+            .service(web::resource("/set_min_karma").to(set_min_karma))
+            .service(web::resource("/set_user_karma").to(set_user_karma))
+    }
+}
@@ -0,0 +1,172 @@
+//! Authentication of the requests sent to the Community Oracle.
+//!
+//! Oracle API is only meant to be used by the Service Provider, so every request must be signed with
+//! the secret shared between these two applications. Signed request contains the following headers:
+//!
+//! - `X-Oracle-Timestamp`: UNIX timestamp (in seconds) of the moment request was created.
+//! - `X-Oracle-Nonce`: unique random string.
+//! - `X-Oracle-Signature`: hex-encoded HMAC-SHA256 of the `"{timestamp}\n{nonce}\n{path}\n{body}"` string.
+//!
+//! Requests which are older than `MAX_REQUEST_AGE` or have the nonce that was already used are rejected,
+//! so a captured request can't be replayed.
+
+use crate::responses::ErrorResponse;
+use actix_web::{dev::Payload, error::InternalError, web, FromRequest, HttpRequest, HttpResponse};
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+
+pub const TIMESTAMP_HEADER: &str = "X-Oracle-Timestamp";
+pub const NONCE_HEADER: &str = "X-Oracle-Nonce";
+pub const SIGNATURE_HEADER: &str = "X-Oracle-Signature";
+
+/// Maximum allowed difference (in seconds) between the request timestamp and the current time.
+pub const MAX_REQUEST_AGE: u64 = 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Missing or malformed authentication header: {0}")]
+    MissingHeader(&'static str),
+    #[error("Request is too old or has timestamp in the future")]
+    StaleRequest,
+    #[error("Request nonce was already used")]
+    ReusedNonce,
+    #[error("Incorrect request signature")]
+    IncorrectSignature,
+    #[error("Unable to decode request body: {0}")]
+    IncorrectBody(String),
+}
+
+/// Calculates the signature for the request to the oracle.
+pub fn sign_request(secret: &str, timestamp: u64, nonce: &str, path: &str, body: &[u8]) -> String {
+    let mac = request_mac(secret, timestamp, nonce, path, body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn request_mac(secret: &str, timestamp: u64, nonce: &str, path: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(format!("{}\n{}\n{}\n", timestamp, nonce, path).as_bytes());
+    mac.update(body);
+    mac
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestAuthenticator {
+    secret: String,
+    /// Mapping "nonce" => "timestamp of the request", for requests within the `MAX_REQUEST_AGE` window.
+    /// Shared between the clones, so replay is detected regardless of the worker handling the request.
+    used_nonces: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl RequestAuthenticator {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            used_nonces: Default::default(),
+        }
+    }
+
+    /// Checks that the request is signed with the shared secret, is not stale and was not seen before.
+    pub fn verify(&self, req: &HttpRequest, body: &[u8]) -> Result<(), AuthError> {
+        let timestamp: u64 = header_value(req, TIMESTAMP_HEADER)?
+            .parse()
+            .map_err(|_| AuthError::MissingHeader(TIMESTAMP_HEADER))?;
+        let nonce = header_value(req, NONCE_HEADER)?;
+        let signature = hex::decode(header_value(req, SIGNATURE_HEADER)?)
+            .map_err(|_| AuthError::MissingHeader(SIGNATURE_HEADER))?;
+
+        let current_time = Utc::now().timestamp() as u64;
+        let request_age = if current_time > timestamp {
+            current_time - timestamp
+        } else {
+            timestamp - current_time
+        };
+        if request_age > MAX_REQUEST_AGE {
+            return Err(AuthError::StaleRequest);
+        }
+
+        request_mac(&self.secret, timestamp, nonce, req.path(), body)
+            .verify(&signature)
+            .map_err(|_| AuthError::IncorrectSignature)?;
+
+        // Nonce is only remembered after the signature check, so unauthenticated requests
+        // can't fill the storage.
+        let mut used_nonces = self.used_nonces.lock().unwrap();
+        used_nonces.retain(|_, &mut used_at| used_at + 2 * MAX_REQUEST_AGE >= current_time);
+        if used_nonces.insert(nonce.to_string(), timestamp).is_some() {
+            return Err(AuthError::ReusedNonce);
+        }
+
+        Ok(())
+    }
+}
+
+fn header_value<'a>(req: &'a HttpRequest, header: &'static str) -> Result<&'a str, AuthError> {
+    req.headers()
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AuthError::MissingHeader(header))
+}
+
+/// Extractor for the JSON request body which is only successful if the request is authenticated
+/// (see the module documentation for details).
+///
+/// Requires `RequestAuthenticator` to be registered as the application data.
+#[derive(Debug)]
+pub struct Authenticated<T>(pub T);
+
+impl<T> Authenticated<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Authenticated<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let body = web::Bytes::from_request(&req, payload);
+
+        Box::pin(async move {
+            let body = body.await?;
+
+            let authenticator = req
+                .app_data::<web::Data<RequestAuthenticator>>()
+                .expect("RequestAuthenticator is not registered");
+
+            if let Err(error) = authenticator.verify(&req, &body) {
+                return Err(unauthorized(error));
+            }
+
+            serde_json::from_slice(&body)
+                .map(Authenticated)
+                .map_err(|error| {
+                    let error = AuthError::IncorrectBody(error.to_string());
+                    let response =
+                        HttpResponse::BadRequest().json(ErrorResponse::error(&error.to_string()));
+                    InternalError::from_response(error, response).into()
+                })
+        })
+    }
+}
+
+fn unauthorized(error: AuthError) -> actix_web::Error {
+    log::warn!("Rejected unauthenticated request: {}", error);
+    let response = HttpResponse::Unauthorized().json(ErrorResponse::error(&error.to_string()));
+
+    InternalError::from_response(error, response).into()
+}
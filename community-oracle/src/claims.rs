@@ -0,0 +1,89 @@
+//! Registry of the token grants claimed by users.
+//!
+//! Every time oracle signs a minting transaction, the claim is recorded for the current round.
+//! Only one claim per round is stored: if user requests a signature for the same round again
+//! (e.g. because the previous minting transaction has expired), the record is replaced, unless
+//! the previous minting transaction has been executed (see `CommunityOracle::minting_signature`).
+
+use crate::{round::GrantRound, zksync::Address};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimRecord {
+    pub round: GrantRound,
+    pub amount: u64,
+    /// Hash of the signed minting transaction. Not set while the custodial mint is in progress.
+    pub tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Claims {
+    /// Mapping "(user, community name)" => "round" => "claim record".
+    records: Arc<RwLock<HashMap<(Address, String), BTreeMap<GrantRound, ClaimRecord>>>>,
+}
+
+impl Claims {
+    pub fn record(&self, user: Address, community_name: &str, record: ClaimRecord) {
+        let mut records = self.records.write().unwrap();
+
+        records
+            .entry((user, community_name.to_string()))
+            .or_default()
+            .insert(record.round.clone(), record);
+    }
+
+    /// Records the claim unless there is a claim for the same round already, in which case `false` is returned.
+    /// Check and insertion are done under the same lock, so the round can be reserved only once.
+    pub fn reserve(&self, user: Address, community_name: &str, record: ClaimRecord) -> bool {
+        let mut records = self.records.write().unwrap();
+
+        let claims = records
+            .entry((user, community_name.to_string()))
+            .or_default();
+        if claims.contains_key(&record.round) {
+            return false;
+        }
+        claims.insert(record.round.clone(), record);
+
+        true
+    }
+
+    /// Removes the claim of the user in the community for the provided round, e.g. if the reserved
+    /// custodial mint has failed.
+    pub fn remove(&self, user: Address, community_name: &str, round: &str) {
+        let mut records = self.records.write().unwrap();
+
+        if let Some(claims) = records.get_mut(&(user, community_name.to_string())) {
+            claims.remove(round);
+        }
+    }
+
+    /// Returns the claim of the user in the community for the provided round, if any.
+    pub fn round_claim(
+        &self,
+        user: Address,
+        community_name: &str,
+        round: &str,
+    ) -> Option<ClaimRecord> {
+        let records = self.records.read().unwrap();
+
+        records
+            .get(&(user, community_name.to_string()))
+            .and_then(|claims| claims.get(round).cloned())
+    }
+
+    /// Returns claims of the user in the community, ordered from the oldest round to the newest one.
+    pub fn user_claims(&self, user: Address, community_name: &str) -> Vec<ClaimRecord> {
+        let records = self.records.read().unwrap();
+
+        records
+            .get(&(user, community_name.to_string()))
+            .map(|claims| claims.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
@@ -1,19 +1,46 @@
+//! Core logic of the Community Oracle.
+//!
+//! Methods of `CommunityOracle` don't depend on the transport, so they can be called either
+//! through the HTTP API (see the `api` module) or directly by the application hosting the oracle in-process.
+
 use crate::{
+    admin::AdminAuthenticator,
+    auth::RequestAuthenticator,
+    claims::{ClaimRecord, Claims},
     config::AppConfig,
-    requests::{GrantedTokensRequest, MintingSignatureRequest, RelatedCommunitiesRequest},
+    eligibility::EligibilityRules,
+    error::RequestError,
+    requests::{
+        AllowlistModeRequest, BatchMintingSignatureRequest, ClaimStatusRequest,
+        CommunityUserRequest, CustodialMintRequest, DeclareCommunityRequest,
+        EligibilityRulesRequest, GrantedTokensRequest, MinKarmaRequest, MintingSignatureRequest,
+        MintingSignatureVerificationRequest, RegisterSigningKeyRequest, RelatedCommunitiesRequest,
+        RemoveCommunityRequest, UserKarmaRequest,
+    },
     responses::{
-        ErrorResponse, GrantedTokensResponse, MintingSignatureResponse, RelatedCommunitiesResponse,
+        BatchMintingSignatureItem, BatchMintingSignatureResponse, ClaimRoundStatus,
+        ClaimStatusResponse, CommunitiesResponse, CommunityDescription, CustodialMintResponse,
+        DeclareCommunityResponse, GenesisWalletAddressResponse, GrantedTokensResponse,
+        MintingSignatureResponse, MintingSignatureVerificationResponse, RegisterSigningKeyResponse,
+        RelatedCommunitiesResponse,
     },
-    zksync::{Address, MintingApi},
+    round::{current_round, is_valid_round},
+    zksync::{minting_tx_hash, Address, CustodialMintError, MintingApi, MintingSignature},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
 };
-use actix_web::{web, HttpResponse, Responder, Scope};
-use std::{collections::HashMap, sync::Arc};
 
 pub const DEFAULT_TOKENS_AMOUNT: u64 = 10_000;
 
 const TEST_COMMUNITY_NAME: &str = "TestCommunity";
 const TEST_COMMUNITY_TOKEN: &str = "MLTT";
 
+/// Communities known to the oracle without being declared by the Service Provider.
+/// They can't be removed, since the oracle relies on them being available.
+pub const BUILT_IN_COMMUNITIES: &[&str] = &[TEST_COMMUNITY_NAME];
+
 #[derive(Debug, Clone)]
 pub struct CommunityInfo {
     token_id: u16,
@@ -27,114 +54,512 @@ impl CommunityInfo {
             token_symbol: token_symbol.into(),
         }
     }
+
+    fn describe(&self, name: &str) -> CommunityDescription {
+        CommunityDescription {
+            name: name.into(),
+            token_id: self.token_id,
+            token_symbol: self.token_symbol.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CommunityOracle {
-    /// Mapping "community name" => "token symbol"
-    known_communities: HashMap<String, CommunityInfo>,
+    /// Mapping "community name" => "community token".
+    /// Communities are declared by the Service Provider, see `CommunityOracle::declare_community`.
+    known_communities: Arc<RwLock<HashMap<String, CommunityInfo>>>,
+    /// Mapping "community name" => "eligibility rules". Communities without an entry have no restrictions.
+    eligibility_rules: Arc<RwLock<HashMap<String, EligibilityRules>>>,
+    claims: Claims,
     minter: Arc<MintingApi>,
     tokens_amount: u64,
     genesis_wallet_address: Address,
+    pub(crate) authenticator: RequestAuthenticator,
+    pub(crate) admin_authenticator: AdminAuthenticator,
 }
 
 impl CommunityOracle {
     pub fn new(config: AppConfig) -> Self {
         let genesis_wallet_address = config.genesis_account_address;
+        let authenticator = RequestAuthenticator::new(config.api_secret.clone());
+        let admin_authenticator = AdminAuthenticator::new(config.admin_api_keys.clone());
         let known_communities = vec![(
             TEST_COMMUNITY_NAME.to_string(),
             CommunityInfo::new(0, TEST_COMMUNITY_TOKEN),
         )];
 
         CommunityOracle {
-            known_communities: known_communities.into_iter().collect(),
+            known_communities: Arc::new(RwLock::new(known_communities.into_iter().collect())),
+            eligibility_rules: Default::default(),
+            claims: Default::default(),
             minter: Arc::new(MintingApi::new(config)),
             tokens_amount: DEFAULT_TOKENS_AMOUNT,
             genesis_wallet_address,
+            authenticator,
+            admin_authenticator,
         }
     }
 
+    /// Returns the information about the community, or an error if community is not known.
+    fn community_info(&self, community_name: &str) -> Result<CommunityInfo, RequestError> {
+        self.known_communities
+            .read()
+            .unwrap()
+            .get(community_name)
+            .cloned()
+            .ok_or_else(|| RequestError::rejected("Invalid community"))
+    }
+
+    /// Checks whether user is eligible to receive tokens of the community in the current round.
+    fn check_eligibility(&self, community_name: &str, user: &Address) -> Result<(), RequestError> {
+        let eligibility_rules = self.eligibility_rules.read().unwrap();
+
+        match eligibility_rules.get(community_name) {
+            Some(rules) => rules
+                .check(user, &current_round())
+                .map_err(|error| RequestError::rejected(&error.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Finds the eligibility rules of the community given its name, and applies the mutating function to them.
+    fn modify_eligibility_rules<F>(&self, community_name: &str, f: F) -> Result<(), RequestError>
+    where
+        F: FnOnce(&mut EligibilityRules),
+    {
+        self.community_info(community_name)?;
+
+        let mut eligibility_rules = self.eligibility_rules.write().unwrap();
+        f(eligibility_rules
+            .entry(community_name.to_string())
+            .or_default());
+
+        Ok(())
+    }
+
+    /// Returns the hashes of the transactions executed by user, see `MintingApi::executed_tx_hashes`.
+    async fn executed_tx_hashes(&self, user: Address) -> Result<HashSet<String>, RequestError> {
+        self.minter.executed_tx_hashes(user).await.map_err(|error| {
+            log::error!(
+                "Unable to check the minting txs execution status: {}",
+                error
+            );
+            RequestError::internal("Unable to retrieve data from the zkSync server")
+        })
+    }
+
     pub async fn tokens_for_user(
-        oracle: web::Data<Self>,
-        request: web::Json<GrantedTokensRequest>,
-    ) -> impl Responder {
-        let request = request.into_inner();
-
-        let community_info = match oracle.known_communities.get(&request.community_name) {
-            Some(info) => info,
-            None => {
-                let error = ErrorResponse::error("Invalid community");
-                return HttpResponse::BadRequest().json(error);
+        &self,
+        request: GrantedTokensRequest,
+    ) -> Result<GrantedTokensResponse, RequestError> {
+        let community_info = self.community_info(&request.community_name)?;
+
+        self.check_eligibility(&request.community_name, &request.user)?;
+
+        Ok(GrantedTokensResponse {
+            token: community_info.token_symbol,
+            amount: self.tokens_amount,
+        })
+    }
+
+    /// Checks the minting transaction and signs it if it's correct. Signature may be requested again
+    /// within the same round, but only until the minting transaction of the round is executed.
+    async fn minting_signature(
+        &self,
+        request: MintingSignatureRequest,
+    ) -> Result<MintingSignature, RequestError> {
+        self.community_info(&request.community_name)?;
+
+        self.check_eligibility(&request.community_name, &request.user)?;
+
+        if !self
+            .minter
+            .is_minting_transaction_correct(&request.minting_tx, &request.user)
+        {
+            return Err(RequestError::rejected("Incorrect minting tx"));
+        }
+
+        let round = current_round();
+        if let Some(previous) =
+            self.claims
+                .round_claim(request.user, &request.community_name, &round)
+        {
+            // Claim without a tx hash is a custodial mint in progress.
+            let executed = match previous.tx_hash {
+                Some(tx_hash) => self
+                    .executed_tx_hashes(request.user)
+                    .await?
+                    .contains(&tx_hash),
+                None => true,
+            };
+            if executed {
+                return Err(RequestError::rejected(
+                    "Tokens for the current round were already claimed",
+                ));
             }
-        };
+        }
 
-        let response = GrantedTokensResponse {
-            token: community_info.token_symbol.clone(),
-            amount: oracle.tokens_amount,
+        let claim = ClaimRecord {
+            round,
+            amount: self.tokens_amount,
+            tx_hash: Some(minting_tx_hash(&request.minting_tx)),
         };
+        self.claims
+            .record(request.user, &request.community_name, claim);
 
-        HttpResponse::Ok().json(response)
+        Ok(self.minter.sign_minting_tx(request.minting_tx))
     }
 
     pub async fn sign_minting_tx(
-        oracle: web::Data<Self>,
-        request: web::Json<MintingSignatureRequest>,
-    ) -> impl Responder {
-        let request = request.into_inner();
-
-        match oracle.known_communities.get(&request.community_name) {
-            Some(_) => {
-                // OK, community is known.
-            }
-            None => {
-                let error = ErrorResponse::error("Invalid community");
-                return HttpResponse::BadRequest().json(error);
-            }
+        &self,
+        request: MintingSignatureRequest,
+    ) -> Result<MintingSignatureResponse, RequestError> {
+        let signature = self.minting_signature(request).await?;
+
+        Ok(MintingSignatureResponse { signature })
+    }
+
+    pub async fn sign_minting_txs_batch(
+        &self,
+        request: BatchMintingSignatureRequest,
+    ) -> Result<BatchMintingSignatureResponse, RequestError> {
+        let mut results = Vec::with_capacity(request.requests.len());
+        for item in request.requests {
+            let community_name = item.community_name.clone();
+
+            let result = match self.minting_signature(item).await {
+                Ok(signature) => BatchMintingSignatureItem {
+                    community_name,
+                    signature: Some(signature),
+                    error_description: None,
+                },
+                Err(error) => BatchMintingSignatureItem {
+                    community_name,
+                    signature: None,
+                    error_description: Some(error.to_string()),
+                },
+            };
+            results.push(result);
+        }
+
+        Ok(BatchMintingSignatureResponse { results })
+    }
+
+    pub async fn verify_minting_signature(
+        &self,
+        request: MintingSignatureVerificationRequest,
+    ) -> Result<MintingSignatureVerificationResponse, RequestError> {
+        self.community_info(&request.community_name)?;
+
+        let issued_by_oracle = self
+            .minter
+            .is_signature_issued_by_oracle(&request.minting_tx, &request.signature);
+        let within_validity_window = self.minter.is_within_validity_window(&request.minting_tx);
+        let executed = self
+            .minter
+            .is_minting_tx_executed(&request.minting_tx)
+            .await
+            .map_err(|error| {
+                log::error!("Unable to check the minting tx execution status: {}", error);
+                RequestError::internal("Unable to retrieve data from the zkSync server")
+            })?;
+
+        Ok(MintingSignatureVerificationResponse {
+            issued_by_oracle,
+            within_validity_window,
+            executed,
+        })
+    }
+
+    pub async fn claim_status(
+        &self,
+        request: ClaimStatusRequest,
+    ) -> Result<ClaimStatusResponse, RequestError> {
+        self.community_info(&request.community_name)?;
+
+        let claims = self
+            .claims
+            .user_claims(request.user, &request.community_name);
+
+        let executed_txs = if claims.is_empty() {
+            Default::default()
+        } else {
+            self.executed_tx_hashes(request.user).await?
         };
 
-        if !oracle
+        let mut rounds: Vec<_> = claims
+            .into_iter()
+            .map(|claim| ClaimRoundStatus {
+                executed: claim
+                    .tx_hash
+                    .as_ref()
+                    .map_or(false, |tx_hash| executed_txs.contains(tx_hash)),
+                round: claim.round,
+                amount: claim.amount,
+                signature_issued: claim.tx_hash.is_some(),
+                tx_hash: claim.tx_hash,
+            })
+            .collect();
+
+        // If tokens for the current round were not claimed yet, report the pending grant as well.
+        let current_round = current_round();
+        if rounds.last().map(|status| &status.round) != Some(&current_round) {
+            let amount = match self.check_eligibility(&request.community_name, &request.user) {
+                Ok(()) => self.tokens_amount,
+                Err(_) => 0,
+            };
+
+            rounds.push(ClaimRoundStatus {
+                round: current_round,
+                amount,
+                signature_issued: false,
+                tx_hash: None,
+                executed: false,
+            });
+        }
+
+        Ok(ClaimStatusResponse { rounds })
+    }
+
+    pub async fn register_signing_key(
+        &self,
+        request: RegisterSigningKeyRequest,
+    ) -> Result<RegisterSigningKeyResponse, RequestError> {
+        let pub_key_hash = self
             .minter
-            .is_minting_transaction_correct(&request.minting_tx, &request.user)
+            .register_custodial_account(request.user)
+            .await
+            .map_err(Self::custodial_error)?;
+
+        Ok(RegisterSigningKeyResponse { pub_key_hash })
+    }
+
+    pub async fn custodial_mint(
+        &self,
+        request: CustodialMintRequest,
+    ) -> Result<CustodialMintResponse, RequestError> {
+        let community_info = self.community_info(&request.community_name)?;
+
+        self.check_eligibility(&request.community_name, &request.user)?;
+
+        // Unlike signatures, custodial mint results in tokens being actually minted, so it can
+        // only be performed once per round. Round is reserved before the minting tx is sent,
+        // so that concurrent requests can't both mint.
+        let round = current_round();
+        let reservation = ClaimRecord {
+            round: round.clone(),
+            amount: self.tokens_amount,
+            tx_hash: None,
+        };
+        if !self
+            .claims
+            .reserve(request.user, &request.community_name, reservation)
         {
-            let error = ErrorResponse::error("Incorrect minting tx");
-            return HttpResponse::BadRequest().json(error);
+            return Err(RequestError::rejected(
+                "Tokens for the current round were already claimed",
+            ));
         }
 
-        let signature = oracle.minter.sign_minting_tx(request.minting_tx);
+        let result = self
+            .minter
+            .custodial_mint(
+                request.user,
+                community_info.token_id,
+                &community_info.token_symbol,
+                self.tokens_amount,
+            )
+            .await;
+        let tx_hash = match result {
+            Ok(tx_hash) => tx_hash,
+            Err(error) => {
+                self.claims
+                    .remove(request.user, &request.community_name, &round);
+                return Err(Self::custodial_error(error));
+            }
+        };
 
-        let response = MintingSignatureResponse { signature };
+        let claim = ClaimRecord {
+            round,
+            amount: self.tokens_amount,
+            tx_hash: Some(tx_hash.clone()),
+        };
+        self.claims
+            .record(request.user, &request.community_name, claim);
 
-        HttpResponse::Ok().json(response)
+        Ok(CustodialMintResponse { tx_hash })
+    }
+
+    /// Converts the custodial mint error into the `RequestError`.
+    fn custodial_error(error: CustodialMintError) -> RequestError {
+        match error {
+            CustodialMintError::Zksync(error) => {
+                log::error!("Custodial mint failed: {}", error);
+                RequestError::internal("Unable to interact with the zkSync server")
+            }
+            error => RequestError::rejected(&error.to_string()),
+        }
     }
 
     pub async fn related_communities(
-        _oracle: web::Data<Self>,
-        _request: web::Json<RelatedCommunitiesRequest>,
-    ) -> impl Responder {
-        let response = RelatedCommunitiesResponse {
-            communities: vec![TEST_COMMUNITY_NAME.into()],
-        };
+        &self,
+        _request: RelatedCommunitiesRequest,
+    ) -> Result<RelatedCommunitiesResponse, RequestError> {
+        // Every user is considered related to every known community.
+        let mut communities: Vec<_> = self
+            .known_communities
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        communities.sort();
 
-        HttpResponse::Ok().json(response)
+        Ok(RelatedCommunitiesResponse { communities })
     }
 
     pub async fn genesis_wallet_address(
-        oracle: web::Data<Self>,
-        _request: web::Json<()>,
-    ) -> impl Responder {
-        let response = serde_json::json!({
-            "address": oracle.genesis_wallet_address,
-        });
-
-        HttpResponse::Ok().json(response)
-    }
-
-    pub fn into_web_scope(self) -> Scope {
-        web::scope("api/v0.1/")
-            .data(self)
-            .service(web::resource("/granted_tokens").to(Self::tokens_for_user))
-            .service(web::resource("/get_minting_signature").to(Self::sign_minting_tx))
-            .service(web::resource("/related_communities").to(Self::related_communities))
-            .service(web::resource("/genesis_wallet_address").to(Self::genesis_wallet_address))
+        &self,
+    ) -> Result<GenesisWalletAddressResponse, RequestError> {
+        Ok(GenesisWalletAddressResponse {
+            address: self.genesis_wallet_address,
+        })
+    }
+
+    pub async fn eligibility_rules(
+        &self,
+        request: EligibilityRulesRequest,
+    ) -> Result<EligibilityRules, RequestError> {
+        self.community_info(&request.community_name)?;
+
+        let eligibility_rules = self.eligibility_rules.read().unwrap();
+        let rules = eligibility_rules
+            .get(&request.community_name)
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(rules)
+    }
+
+    pub async fn ban_user(&self, request: CommunityUserRequest) -> Result<(), RequestError> {
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.banned.insert(request.user);
+        })
+    }
+
+    pub async fn unban_user(&self, request: CommunityUserRequest) -> Result<(), RequestError> {
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.banned.remove(&request.user);
+        })
+    }
+
+    pub async fn set_allowlist_mode(
+        &self,
+        request: AllowlistModeRequest,
+    ) -> Result<(), RequestError> {
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.allowlist_enabled = request.enabled;
+        })
+    }
+
+    pub async fn allow_user(&self, request: CommunityUserRequest) -> Result<(), RequestError> {
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.allowlist.insert(request.user);
+        })
+    }
+
+    pub async fn disallow_user(&self, request: CommunityUserRequest) -> Result<(), RequestError> {
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.allowlist.remove(&request.user);
+        })
+    }
+
+    pub async fn set_min_karma(&self, request: MinKarmaRequest) -> Result<(), RequestError> {
+        let round = request.round.unwrap_or_else(current_round);
+        if !is_valid_round(&round) {
+            return Err(RequestError::rejected("Round must be in the YYYY-MM form"));
+        }
+
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.min_karma.insert(round, request.min_karma);
+        })
+    }
+
+    pub async fn set_user_karma(&self, request: UserKarmaRequest) -> Result<(), RequestError> {
+        self.modify_eligibility_rules(&request.community_name, |rules| {
+            rules.karma.insert(request.user, request.karma);
+        })
+    }
+
+    /// Declares a new community or updates the token of the already known one.
+    pub async fn declare_community(
+        &self,
+        request: DeclareCommunityRequest,
+    ) -> Result<DeclareCommunityResponse, RequestError> {
+        if request.community_name.is_empty() || request.token_symbol.is_empty() {
+            return Err(RequestError::rejected(
+                "Community name and token symbol must not be empty",
+            ));
+        }
+
+        let community_info = CommunityInfo::new(request.token_id, request.token_symbol);
+        let previous = self
+            .known_communities
+            .write()
+            .unwrap()
+            .insert(request.community_name.clone(), community_info)
+            .map(|info| info.describe(&request.community_name));
+
+        Ok(DeclareCommunityResponse { previous })
+    }
+
+    /// Removes the community along with its eligibility rules. Built-in communities can't be removed.
+    /// Claims history is kept, so it will be taken into account if community is declared again.
+    pub async fn remove_community(
+        &self,
+        request: RemoveCommunityRequest,
+    ) -> Result<(), RequestError> {
+        if BUILT_IN_COMMUNITIES.contains(&request.community_name.as_str()) {
+            return Err(RequestError::rejected(
+                "Built-in community can't be removed",
+            ));
+        }
+
+        if self
+            .known_communities
+            .write()
+            .unwrap()
+            .remove(&request.community_name)
+            .is_none()
+        {
+            return Err(RequestError::rejected("Invalid community"));
+        }
+
+        self.eligibility_rules
+            .write()
+            .unwrap()
+            .remove(&request.community_name);
+
+        Ok(())
+    }
+
+    pub async fn communities(&self) -> Result<CommunitiesResponse, RequestError> {
+        let mut communities: Vec<_> = self
+            .known_communities
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, info)| info.describe(name))
+            .collect();
+        communities.sort_by(|a, b| a.name.cmp(&b.name));
+        let built_in = BUILT_IN_COMMUNITIES
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        Ok(CommunitiesResponse {
+            communities,
+            built_in,
+        })
     }
 }
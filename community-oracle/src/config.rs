@@ -1,4 +1,4 @@
-use crate::zksync::Address;
+use crate::{admin::AdminApiKey, zksync::Address};
 use serde_derive::Deserialize;
 use std::env;
 use std::path::PathBuf;
@@ -9,6 +9,22 @@ pub struct AppConfig {
     pub genesis_account_address: Address,
     pub genesis_account_private_key: String,
     pub genesis_account_eth_private_key: String,
+    pub zksync_rest_api_address: String,
+    pub zksync_json_rpc_address: String,
+    /// Timeout (in milliseconds) for a single request to the zkSync server.
+    #[serde(default = "default_zksync_request_timeout_ms")]
+    pub zksync_request_timeout_ms: u64,
+    /// Secret shared with the Service Provider, used to authenticate requests.
+    pub api_secret: String,
+    /// Address on which the admin API is served. Must not be exposed to the public.
+    #[serde(default = "default_admin_bind_address")]
+    pub admin_bind_address: String,
+    /// API keys allowed to access the admin API, each with its role.
+    #[serde(default)]
+    pub admin_api_keys: Vec<AdminApiKey>,
+    /// Secret the zkSync signing keys for the custodial mint are derived from. Changing it invalidates
+    /// the keys set by users, so it must be kept the same.
+    pub custodial_key_secret: String,
 }
 
 impl AppConfig {
@@ -36,10 +52,39 @@ impl AppConfig {
                 .expect("GENESIS_ACCOUNT_PRIVATE_KEY"),
             genesis_account_eth_private_key: env::var("GENESIS_ACCOUNT_ETH_PRIVATE_KEY")
                 .expect("GENESIS_ACCOUNT_ETH_PRIVATE_KEY"),
+            zksync_rest_api_address: env::var("ZKSYNC_REST_API_ADDRESS")
+                .expect("ZKSYNC_REST_API_ADDRESS"),
+            zksync_json_rpc_address: env::var("ZKSYNC_JSON_RPC_ADDRESS")
+                .expect("ZKSYNC_JSON_RPC_ADDRESS"),
+            zksync_request_timeout_ms: env::var("ZKSYNC_REQUEST_TIMEOUT_MS")
+                .map(|timeout| timeout.parse().expect("ZKSYNC_REQUEST_TIMEOUT_MS"))
+                .unwrap_or_else(|_| default_zksync_request_timeout_ms()),
+            api_secret: env::var("COMMUNITY_ORACLE_API_SECRET")
+                .expect("COMMUNITY_ORACLE_API_SECRET"),
+            admin_bind_address: env::var("ORACLE_ADMIN_BIND_ADDRESS")
+                .unwrap_or_else(|_| default_admin_bind_address()),
+            admin_api_keys: env::var("ORACLE_ADMIN_API_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|key| !key.trim().is_empty())
+                .map(|key| {
+                    key.parse()
+                        .unwrap_or_else(|err| panic!("Can't decode ORACLE_ADMIN_API_KEYS: {}", err))
+                })
+                .collect(),
+            custodial_key_secret: env::var("CUSTODIAL_KEY_SECRET").expect("CUSTODIAL_KEY_SECRET"),
         }
     }
 }
 
+fn default_zksync_request_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_admin_bind_address() -> String {
+    "127.0.0.1:4041".into()
+}
+
 fn load_json<T: serde::de::DeserializeOwned>(filepath: &PathBuf) -> T {
     let buffer = std::fs::read_to_string(filepath).expect("Failed to read the test spec file");
     serde_json::from_str(&buffer).expect(
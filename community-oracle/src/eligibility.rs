@@ -0,0 +1,59 @@
+//! Per-community eligibility rules for the token grants.
+//!
+//! Before granting tokens or signing a minting transaction, oracle checks whether user is
+//! allowed to receive tokens of the community. The following rules are applied (in order):
+//!
+//! - Users from the ban list are never eligible.
+//! - If the allowlist mode is enabled (e.g. for closed betas), only users from the allowlist are eligible.
+//! - If the minimum karma threshold is set for the current round, users with karma below it are not eligible.
+
+use crate::{round::GrantRound, zksync::Address};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EligibilityError {
+    #[error("User is banned in the community")]
+    Banned,
+    #[error("User is not in the allowlist of the community")]
+    NotInAllowlist,
+    #[error("User karma is too low for the current round (required {required}, got {actual})")]
+    InsufficientKarma { required: u64, actual: u64 },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EligibilityRules {
+    /// Users that are not allowed to receive community tokens.
+    pub banned: HashSet<Address>,
+    /// If set to `true`, only users from the `allowlist` may receive community tokens.
+    pub allowlist_enabled: bool,
+    pub allowlist: HashSet<Address>,
+    /// Mapping "round" => "minimum karma required to receive tokens in this round".
+    pub min_karma: HashMap<GrantRound, u64>,
+    /// Known karma of users in the community. Users not in this list are considered to have zero karma.
+    pub karma: HashMap<Address, u64>,
+}
+
+impl EligibilityRules {
+    /// Checks whether user is eligible to receive community tokens in the provided round.
+    pub fn check(&self, user: &Address, round: &str) -> Result<(), EligibilityError> {
+        if self.banned.contains(user) {
+            return Err(EligibilityError::Banned);
+        }
+
+        if self.allowlist_enabled && !self.allowlist.contains(user) {
+            return Err(EligibilityError::NotInAllowlist);
+        }
+
+        if let Some(&required) = self.min_karma.get(round) {
+            let actual = self.karma.get(user).copied().unwrap_or_default();
+            if actual < required {
+                return Err(EligibilityError::InsufficientKarma { required, actual });
+            }
+        }
+
+        Ok(())
+    }
+}
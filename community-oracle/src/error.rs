@@ -0,0 +1,24 @@
+//! Errors of the Community Oracle request processing.
+
+use crate::responses::ErrorResponse;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum RequestError {
+    /// Request is incorrect (e.g. community is unknown or user is not eligible for tokens).
+    #[error("{}", .0.error_description)]
+    Rejected(ErrorResponse),
+    /// Request is correct, but oracle wasn't able to process it (e.g. zkSync server is unavailable).
+    #[error("{}", .0.error_description)]
+    Internal(ErrorResponse),
+}
+
+impl RequestError {
+    pub fn rejected(message: &str) -> Self {
+        Self::Rejected(ErrorResponse::error(message))
+    }
+
+    pub fn internal(message: &str) -> Self {
+        Self::Internal(ErrorResponse::error(message))
+    }
+}
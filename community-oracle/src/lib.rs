@@ -1,5 +1,12 @@
+pub mod admin;
+pub mod api;
+pub mod auth;
+pub mod claims;
 pub mod community_oracle;
 pub mod config;
+pub mod eligibility;
+pub mod error;
 pub mod requests;
 pub mod responses;
+pub mod round;
 pub mod zksync;
@@ -4,16 +4,30 @@ use std::path::PathBuf;
 use structopt::StructOpt;
 
 async fn run_server(config: AppConfig, bind_address: &str) -> std::io::Result<()> {
+    let admin_bind_address = config.admin_bind_address.clone();
     let community_oracle = CommunityOracle::new(config);
 
-    HttpServer::new(move || {
-        let oracle = community_oracle.clone();
+    let api_oracle = community_oracle.clone();
+    let api_server = HttpServer::new(move || {
+        let oracle = api_oracle.clone();
         let app = oracle.into_web_scope();
         App::new().service(app)
     })
     .bind(bind_address)?
-    .run()
-    .await
+    .run();
+
+    // Admin API is served separately, so it's not reachable by the Service Provider credentials.
+    let admin_server = HttpServer::new(move || {
+        let oracle = community_oracle.clone();
+        let app = oracle.into_admin_scope();
+        App::new().service(app)
+    })
+    .bind(&admin_bind_address)?
+    .run();
+
+    tokio::try_join!(api_server, admin_server)?;
+
+    Ok(())
 }
 
 #[derive(Debug, StructOpt)]
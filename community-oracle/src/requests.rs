@@ -1,4 +1,7 @@
-use crate::zksync::{Address, MintingTransaction};
+use crate::{
+    round::GrantRound,
+    zksync::{Address, MintingSignature, MintingTransaction},
+};
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +19,40 @@ pub struct MintingSignatureRequest {
     pub minting_tx: MintingTransaction,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMintingSignatureRequest {
+    pub requests: Vec<MintingSignatureRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintingSignatureVerificationRequest {
+    pub community_name: String,
+    pub minting_tx: MintingTransaction,
+    pub signature: MintingSignature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimStatusRequest {
+    pub user: Address,
+    pub community_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterSigningKeyRequest {
+    pub user: Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustodialMintRequest {
+    pub user: Address,
+    pub community_name: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RelatedCommunitiesRequest {
@@ -27,3 +64,55 @@ pub struct RelatedCommunitiesRequest {
 pub struct RelatedCommunitiesResponse {
     pub communities: Vec<String>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityUserRequest {
+    pub community_name: String,
+    pub user: Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowlistModeRequest {
+    pub community_name: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinKarmaRequest {
+    pub community_name: String,
+    /// Round to set the threshold for. If not provided, the current round is used.
+    pub round: Option<GrantRound>,
+    pub min_karma: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserKarmaRequest {
+    pub community_name: String,
+    pub user: Address,
+    pub karma: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EligibilityRulesRequest {
+    pub community_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclareCommunityRequest {
+    pub community_name: String,
+    /// ID of the community token in the zkSync network.
+    pub token_id: u16,
+    pub token_symbol: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveCommunityRequest {
+    pub community_name: String,
+}
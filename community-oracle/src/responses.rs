@@ -1,4 +1,7 @@
-use crate::zksync::MintingSignature;
+use crate::{
+    round::GrantRound,
+    zksync::{Address, MintingSignature, PubKeyHash},
+};
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,8 +31,101 @@ pub struct MintingSignatureResponse {
     pub signature: MintingSignature,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Outcome of a single item of the batch minting signature request.
+/// Exactly one of `signature` and `error_description` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMintingSignatureItem {
+    pub community_name: String,
+    pub signature: Option<MintingSignature>,
+    pub error_description: Option<String>,
+}
+
+/// Response for the batch minting signature request. Items are in the same order as in the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMintingSignatureResponse {
+    pub results: Vec<BatchMintingSignatureItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintingSignatureVerificationResponse {
+    /// `true` if the signature was issued by the oracle for the provided minting transaction.
+    pub issued_by_oracle: bool,
+    /// `true` if the minting transaction can be executed at the moment.
+    pub within_validity_window: bool,
+    /// `true` if the minting transaction was already successfully executed in the zkSync network.
+    pub executed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimRoundStatus {
+    pub round: GrantRound,
+    /// Amount of tokens granted to user in this round.
+    pub amount: u64,
+    pub signature_issued: bool,
+    /// Hash of the signed minting transaction (if signature was issued).
+    pub tx_hash: Option<String>,
+    /// `true` if the signed minting transaction was successfully executed in the zkSync network.
+    pub executed: bool,
+}
+
+/// Claims of the user in the community, ordered from the oldest round to the newest one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimStatusResponse {
+    pub rounds: Vec<ClaimRoundStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterSigningKeyResponse {
+    /// Hash of the signing key generated by oracle, which user has to set for the account.
+    pub pub_key_hash: PubKeyHash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustodialMintResponse {
+    /// Hash of the minting transaction sent to the zkSync network.
+    pub tx_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisWalletAddressResponse {
+    pub address: Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RelatedCommunitiesResponse {
     pub communities: Vec<String>,
 }
+
+/// Community known to the oracle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityDescription {
+    pub name: String,
+    pub token_id: u16,
+    pub token_symbol: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclareCommunityResponse {
+    /// Previous description of the community, if it was already declared.
+    pub previous: Option<CommunityDescription>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunitiesResponse {
+    pub communities: Vec<CommunityDescription>,
+    /// Names of the communities known to the oracle without being declared by the Service Provider.
+    #[serde(default)]
+    pub built_in: Vec<String>,
+}
@@ -0,0 +1,18 @@
+//! Token grant rounds.
+//!
+//! Community tokens are granted to users once per round. Currently round is a calendar
+//! month, and it is identified by a string in the `YYYY-MM` form (e.g. `2020-07`).
+
+use chrono::{NaiveDate, Utc};
+
+pub type GrantRound = String;
+
+/// Checks that the round identifier is in the `YYYY-MM` form.
+pub fn is_valid_round(round: &str) -> bool {
+    round.len() == 7 && NaiveDate::parse_from_str(&format!("{}-01", round), "%Y-%m-%d").is_ok()
+}
+
+/// Returns the identifier of the round which is active at the moment.
+pub fn current_round() -> GrantRound {
+    Utc::now().format("%Y-%m").to_string()
+}
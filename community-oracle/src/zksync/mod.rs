@@ -0,0 +1,292 @@
+//! Module encapsulating the interaction with the zkSync network.
+
+// Note:
+// As a gateway, this module uses `testkit` package from the zkSync core. This package
+// is only used because currently there is no official zkSync client library in Rust.
+// Note that the main purpose of `testkit` crate is to provide the *testing* infrastructure
+// rather than a full-fledged client experience.
+
+use crate::{
+    config::AppConfig,
+    zksync::{
+        rest_client::{RestApiClient, HISTORY_PAGE_SIZE},
+        rpc_client::RpcClient,
+    },
+};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use serde_derive::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{collections::HashSet, time::Duration};
+use thiserror::Error;
+use zksync_models::node::{
+    tx::{FranklinTx, TransferFrom, TxSignature},
+    AccountId, Nonce, TokenId,
+};
+use zksync_testkit::zksync_account::ZksyncAccount;
+
+mod rest_client;
+mod rpc_client;
+
+// Public re-exports and type declarations to not tie the rest application to the actual zkSync types.
+pub use zksync_models::node::{Address, PrivateKey, PubKeyHash, H256};
+pub type MintingTransaction = TransferFrom;
+
+/// Period (in seconds) during which the minting transaction created by oracle can be executed.
+pub const MINTING_TX_VALIDITY_PERIOD: u64 = 24 * 60 * 60;
+
+#[derive(Error, Debug)]
+pub enum CustodialMintError {
+    #[error("Account does not exist in the zkSync network")]
+    UnknownAccount,
+    #[error("Signing key generated by oracle is not set for the account")]
+    KeyNotSet,
+    #[error("Unable to interact with the zkSync server: {0}")]
+    Zksync(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintingSignature {
+    zksync_signature: TxSignature,
+}
+
+#[derive(Debug)]
+pub struct MintingApi {
+    mint_account: ZksyncAccount,
+    rest_api_client: RestApiClient,
+    rpc_client: RpcClient,
+    /// Secret the signing keys for the custodial mint are derived from, see `custodial_private_key`.
+    custodial_key_secret: String,
+}
+
+impl MintingApi {
+    pub fn new(config: AppConfig) -> Self {
+        // Read zkSync private key for genesis account.
+        let zk_private_key = read_zksync_private_key(&config.genesis_account_private_key)
+            .expect("Incorrect private zkSync key for genesis account");
+
+        // Read Ethereum private key for genesis account.
+        let eth_private_key_bytes = hex::decode(
+            config
+                .genesis_account_eth_private_key
+                .trim_start_matches("0x"),
+        )
+        .expect("Incorrect private Ethereum key for genesis account");
+        let eth_private_key = H256::from_slice(&eth_private_key_bytes);
+
+        let mint_account = ZksyncAccount::new(
+            zk_private_key,
+            0,
+            config.genesis_account_address,
+            eth_private_key,
+        );
+
+        // Set the account ID (required to sign transactions)
+        mint_account.set_account_id(Some(config.genesis_account_id));
+
+        // Requests to zkSync are only bounded by the timeout. They aren't retried here: failures are reported
+        // to the Service Provider, which retries the requests that are safe to repeat and tracks the oracle
+        // availability with its own circuit breaker.
+        let timeout = Duration::from_millis(config.zksync_request_timeout_ms);
+        let rest_api_client = RestApiClient::new(config.zksync_rest_api_address, timeout);
+        let rpc_client = RpcClient::new(config.zksync_json_rpc_address, timeout);
+
+        Self {
+            mint_account,
+            rest_api_client,
+            rpc_client,
+            custodial_key_secret: config.custodial_key_secret,
+        }
+    }
+
+    pub fn is_minting_transaction_correct(
+        &self,
+        tx: &MintingTransaction,
+        address: &Address,
+    ) -> bool {
+        tx.from == self.mint_account.address
+            && tx.to == *address
+            && tx.amount == crate::community_oracle::DEFAULT_TOKENS_AMOUNT.into()
+    }
+
+    pub fn sign_minting_tx(&self, tx: MintingTransaction) -> MintingSignature {
+        let from_signature =
+            TxSignature::sign_musig(&self.mint_account.private_key, &tx.get_bytes());
+
+        MintingSignature {
+            zksync_signature: from_signature,
+        }
+    }
+    /// Checks whether the signature for the minting transaction was produced by the minting account of the oracle.
+    pub fn is_signature_issued_by_oracle(
+        &self,
+        tx: &MintingTransaction,
+        signature: &MintingSignature,
+    ) -> bool {
+        match signature.zksync_signature.verify_musig(&tx.get_bytes()) {
+            Some(pub_key) => PubKeyHash::from_pubkey(&pub_key) == self.mint_account.pubkey_hash,
+            None => false,
+        }
+    }
+
+    /// Checks whether the current time is within the validity window of the minting transaction.
+    pub fn is_within_validity_window(&self, tx: &MintingTransaction) -> bool {
+        let current_time = Utc::now().timestamp() as u64;
+
+        tx.valid_from <= current_time && current_time <= tx.valid_until
+    }
+
+    /// Checks whether the minting transaction was successfully executed in the zkSync network.
+    pub async fn is_minting_tx_executed(&self, tx: &MintingTransaction) -> Result<bool> {
+        // Minting transaction is initiated by the user, so it must be in the user account history.
+        let executed_txs = self.executed_tx_hashes(tx.to).await?;
+
+        Ok(executed_txs.contains(&minting_tx_hash(tx)))
+    }
+
+    /// Returns the normalized hashes of transactions successfully executed by the account.
+    /// The whole history of the account is read page by page.
+    pub async fn executed_tx_hashes(&self, address: Address) -> Result<HashSet<String>> {
+        let mut executed_txs = HashSet::new();
+        let mut offset = 0;
+        loop {
+            let page = self
+                .rest_api_client
+                .get_transactions_history(address, offset, HISTORY_PAGE_SIZE)
+                .await?;
+            let page_size = page.len() as u64;
+
+            executed_txs.extend(
+                page.into_iter()
+                    .filter(|item| item.success == Some(true))
+                    .filter_map(|item| item.hash)
+                    .map(|hash| normalize_tx_hash(&hash)),
+            );
+            if page_size < HISTORY_PAGE_SIZE {
+                return Ok(executed_txs);
+            }
+
+            offset += page_size;
+        }
+    }
+
+    /// Returns the account of user signing the transactions with the key oracle uses for the custodial mint.
+    /// Key is derived from the configured secret and the account address, so it's never stored, and stays
+    /// the same after restart.
+    fn custodial_account(
+        &self,
+        address: Address,
+        account_id: AccountId,
+        nonce: Nonce,
+    ) -> ZksyncAccount {
+        let account = ZksyncAccount::new(
+            custodial_private_key(&self.custodial_key_secret, address),
+            nonce,
+            address,
+            // Ethereum key is only required for the L1 operations, which are never performed by oracle.
+            H256::default(),
+        );
+        account.set_account_id(Some(account_id));
+
+        account
+    }
+
+    /// Returns the hash of the zkSync signing key oracle will use to mint tokens on the user's behalf.
+    /// User has to set it as the signing key of the account (by sending the `ChangePubKey` transaction
+    /// authorized with the user's Ethereum key), so the user's own keys are never shared with oracle.
+    /// Repeated registration returns the same key.
+    pub async fn register_custodial_account(
+        &self,
+        address: Address,
+    ) -> Result<PubKeyHash, CustodialMintError> {
+        let account_info = self.rpc_client.account_info(address).await?;
+        let account_id = account_info.id.ok_or(CustodialMintError::UnknownAccount)?;
+        let account = self.custodial_account(address, account_id, account_info.committed.nonce);
+
+        Ok(account.pubkey_hash.clone())
+    }
+
+    /// Creates a minting transaction for user, signs it with both the user's and the genesis account keys,
+    /// and sends it to the zkSync network. Returns the normalized hash of the sent transaction.
+    pub async fn custodial_mint(
+        &self,
+        user: Address,
+        token_id: TokenId,
+        token_symbol: &str,
+        amount: u64,
+    ) -> Result<String, CustodialMintError> {
+        let fee = self
+            .rpc_client
+            .get_tx_fee("TransferFrom", user, token_symbol)
+            .await?;
+        let account_info = self.rpc_client.account_info(user).await?;
+        let account_id = account_info.id.ok_or(CustodialMintError::UnknownAccount)?;
+        let user_account = self.custodial_account(user, account_id, account_info.committed.nonce);
+        // User may not have set the derived key yet, or have changed it since.
+        if account_info.committed.pub_key_hash != user_account.pubkey_hash {
+            return Err(CustodialMintError::KeyNotSet);
+        }
+
+        let valid_from = Utc::now().timestamp() as u64;
+        let valid_until = valid_from + MINTING_TX_VALIDITY_PERIOD;
+
+        let minting_tx = user_account.sign_transfer_from(
+            &self.mint_account,
+            token_id,
+            amount.into(),
+            fee,
+            Some(account_info.committed.nonce),
+            false,
+            valid_from,
+            valid_until,
+        );
+
+        let tx_hash = self
+            .rpc_client
+            .send_tx(FranklinTx::TransferFrom(Box::new(minting_tx)))
+            .await?;
+
+        Ok(normalize_tx_hash(&tx_hash.to_string()))
+    }
+}
+
+/// Decodes the zkSync private key from its hexadecimal representation.
+fn read_zksync_private_key(private_key: &str) -> Result<PrivateKey> {
+    let private_key_bytes: Vec<_> = hex::decode(private_key.trim_start_matches("0x"))?
+        .into_iter()
+        .rev()
+        .collect();
+
+    PrivateKey::read(&private_key_bytes[..]).map_err(|err| anyhow!("{}", err))
+}
+
+/// Derives the zkSync private key for the custodial mint on behalf of the account.
+fn custodial_private_key(secret: &str, address: Address) -> PrivateKey {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(address.as_ref());
+    let mut key_bytes = mac.finalize().into_bytes();
+
+    // Key is read as a little-endian number, which must be less than the order of the key field.
+    // Clearing the most significant bits of the number ensures that.
+    key_bytes[31] &= 0x03;
+
+    PrivateKey::read(&key_bytes[..]).expect("Derived key is less than the field order")
+}
+
+/// Returns the normalized hash of the minting transaction (see `normalize_tx_hash`).
+pub fn minting_tx_hash(tx: &MintingTransaction) -> String {
+    let hash = FranklinTx::TransferFrom(Box::new(tx.clone())).hash();
+
+    normalize_tx_hash(&hash.to_string())
+}
+
+/// zkSync may represent transaction hashes either with the `sync-tx:` or `0x` prefix, or without any
+/// prefix at all. This function strips the prefix, so hashes can be compared.
+fn normalize_tx_hash(hash: &str) -> String {
+    hash.trim_start_matches("sync-tx:")
+        .trim_start_matches("0x")
+        .to_lowercase()
+}
@@ -0,0 +1,86 @@
+//! REST API client for the zkSync server.
+
+// Built-in imports
+use std::time::Duration;
+// External uses
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+// Workspace uses
+// Local uses
+use crate::zksync::Address;
+
+/// Maximum amount of the history items returned by the zkSync server per request.
+pub const HISTORY_PAGE_SIZE: u64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransactionsHistoryItem {
+    pub tx_id: String,
+    pub hash: Option<String>,
+    pub eth_block: Option<i64>,
+    pub pq_id: Option<i64>,
+    pub tx: serde_json::Value,
+    pub success: Option<bool>,
+    pub fail_reason: Option<String>,
+    pub commited: bool,
+    pub verified: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// `RestApiClient` is capable of interacting with the ZKSync node via its
+/// REST API interface.
+#[derive(Debug, Clone)]
+pub struct RestApiClient {
+    api_addr: String,
+    client: reqwest::Client,
+}
+
+impl RestApiClient {
+    /// Creates a new `RestApiClient` object. Requests which take longer than `timeout` are aborted.
+    pub fn new(api_addr: impl Into<String>, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Unable to create HTTP client");
+
+        Self {
+            api_addr: api_addr.into(),
+            client,
+        }
+    }
+
+    /// Returns `limit` transactions of the account, skipping `offset` newest ones.
+    pub async fn get_transactions_history(
+        &self,
+        address: Address,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<TransactionsHistoryItem>> {
+        let address = format!("0x{}", hex::encode(address.as_ref()));
+
+        let formatted_postfix = format!(
+            "/account/{address}/history/{offset}/{limit}",
+            address = address,
+            offset = offset,
+            limit = limit
+        );
+        let endpoint = self.endpoint(&formatted_postfix);
+
+        let response = self.client.get(&endpoint).send().await?;
+
+        let json_data: Vec<TransactionsHistoryItem> = match response.json().await {
+            Ok(json) => json,
+            Err(error) => {
+                log::error!("zkSync server returned incorrect JSON: {}", error);
+                log::error!("request path: {}", &endpoint);
+                return Err(anyhow!("Unable to decode response from the zkSync server",));
+            }
+        };
+
+        Ok(json_data)
+    }
+
+    fn endpoint(&self, postfix: &str) -> String {
+        format!("{}/api/v0.1{}", self.api_addr, postfix)
+    }
+}
@@ -0,0 +1,174 @@
+//! RPC client for the zkSync server.
+
+// Built-in imports
+use std::{str::FromStr, time::Duration};
+// External uses
+use anyhow::{anyhow, Result};
+use jsonrpc_core::types::response::Output;
+use num::BigUint;
+use serde_derive::Deserialize;
+// Workspace uses
+use zksync_models::node::{
+    tx::{FranklinTx, TxHash},
+    AccountId, Nonce, PubKeyHash,
+};
+// Local uses
+use self::messages::JsonRpcRequest;
+use crate::zksync::Address;
+
+/// State of the zkSync account (either committed or verified).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountState {
+    pub nonce: Nonce,
+    pub pub_key_hash: PubKeyHash,
+}
+
+/// Response of the `account_info` RPC method.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfoResp {
+    pub address: Address,
+    pub id: Option<AccountId>,
+    pub committed: AccountState,
+}
+
+/// Response of the `get_tx_fee` RPC method.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TxFeeResp {
+    total_fee: String,
+}
+
+/// `RpcClient` is capable of interacting with the ZKSync node via its
+/// JSON RPC interface.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    rpc_addr: String,
+    client: reqwest::Client,
+}
+
+impl RpcClient {
+    /// Creates a new `RpcClient` object. Requests which take longer than `timeout` are aborted.
+    pub fn new(rpc_addr: impl Into<String>, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Unable to create HTTP client");
+
+        Self {
+            rpc_addr: rpc_addr.into(),
+            client,
+        }
+    }
+
+    /// Retrieves the information about the account.
+    pub async fn account_info(&self, address: Address) -> Result<AccountInfoResp> {
+        let msg = JsonRpcRequest::account_info(address);
+
+        let ret = self.post(&msg).await?;
+        let account_info = serde_json::from_value(ret)
+            .map_err(|err| anyhow!("failed to parse `account_info` response: {}", err))?;
+        Ok(account_info)
+    }
+
+    /// Retrieves the total fee for the transaction of the given type.
+    pub async fn get_tx_fee(
+        &self,
+        tx_type: &str,
+        address: Address,
+        token_symbol: &str,
+    ) -> Result<BigUint> {
+        let msg = JsonRpcRequest::get_tx_fee(tx_type, address, token_symbol);
+
+        let ret = self.post(&msg).await?;
+        let fee: TxFeeResp = serde_json::from_value(ret)
+            .map_err(|err| anyhow!("failed to parse `get_tx_fee` response: {}", err))?;
+        BigUint::from_str(&fee.total_fee)
+            .map_err(|err| anyhow!("incorrect fee in `get_tx_fee` response: {}", err))
+    }
+
+    /// Sends the transaction to the zkSync network.
+    pub async fn send_tx(&self, tx: FranklinTx) -> Result<TxHash> {
+        let msg = JsonRpcRequest::submit_tx(tx);
+
+        let ret = self.post(&msg).await?;
+        let tx_hash = serde_json::from_value(ret)
+            .map_err(|err| anyhow!("failed to parse `tx_submit` response: {}", err))?;
+        Ok(tx_hash)
+    }
+
+    /// Performs a POST query to the JSON RPC endpoint,
+    /// and decodes the response, returning the decoded `serde_json::Value`.
+    /// `Ok` is returned only for successful calls, for any kind of error
+    /// the `Err` variant is returned (including the failed RPC method
+    /// execution response).
+    async fn post(&self, message: impl serde::Serialize) -> Result<serde_json::Value> {
+        let res = self
+            .client
+            .post(&self.rpc_addr)
+            .json(&message)
+            .send()
+            .await?;
+        if res.status() != reqwest::StatusCode::OK {
+            return Err(anyhow!(
+                "Post query responded with a non-OK response: {}",
+                res.status()
+            ));
+        }
+        let reply: Output = res.json().await?;
+
+        match reply {
+            Output::Success(v) => Ok(v.result),
+            Output::Failure(v) => Err(anyhow!("RPC error: {}", v.error)),
+        }
+    }
+}
+
+/// Structures representing the RPC request messages.
+mod messages {
+    use crate::zksync::Address;
+    use serde_derive::Serialize;
+    use zksync_models::node::tx::FranklinTx;
+
+    #[derive(Debug, Serialize)]
+    pub struct JsonRpcRequest {
+        pub id: String,
+        pub method: String,
+        pub jsonrpc: String,
+        pub params: Vec<serde_json::Value>,
+    }
+
+    impl JsonRpcRequest {
+        fn create(method: impl ToString, params: Vec<serde_json::Value>) -> Self {
+            Self {
+                id: "1".to_owned(),
+                jsonrpc: "2.0".to_owned(),
+                method: method.to_string(),
+                params,
+            }
+        }
+
+        pub fn account_info(address: Address) -> Self {
+            let params = vec![serde_json::to_value(address).expect("serialization fail")];
+            Self::create("account_info", params)
+        }
+
+        pub fn get_tx_fee(tx_type: &str, address: Address, token_symbol: &str) -> Self {
+            let params = vec![
+                serde_json::to_value(tx_type).expect("serialization fail"),
+                serde_json::to_value(address).expect("serialization fail"),
+                serde_json::to_value(token_symbol).expect("serialization fail"),
+            ];
+            Self::create("get_tx_fee", params)
+        }
+
+        pub fn submit_tx(tx: FranklinTx) -> Self {
+            let params = vec![
+                serde_json::to_value(tx).expect("serialization fail"),
+                serde_json::Value::Null,
+            ];
+            Self::create("tx_submit", params)
+        }
+    }
+}
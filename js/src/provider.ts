@@ -1,5 +1,23 @@
 import Axios from "axios";
-import { SubscriptionCheckResponse, GrantedTokensResponse } from "./types";
+import {
+    SubscriptionCheckResponse,
+    GrantedTokensResponse,
+    MintingSignatureRequest,
+    BatchMintingSignatureItem,
+    MintingSignatureVerificationResponse,
+    ClaimRoundStatus,
+    Community,
+    CommunityListFilter,
+    CommunityListResponse,
+    Purchase,
+    EntitlementsResponse,
+    User,
+    ChallengeResponse,
+    LoginResponse,
+    MigrateSubscriptionResponse,
+    ExtendSubscriptionResponse
+} from "./types";
+import { ethers } from "ethers";
 import * as zksync from "zksync";
 
 export class ApiError extends Error {
@@ -12,7 +30,15 @@ export interface ApiErrorObject {
     error: string;
 }
 
+// Returns the message to be signed by the subscription wallet to prove that it belongs to user.
+export function subscriptionWalletMessage(user: zksync.types.Address, communityName: string, subscriptionWallet: zksync.types.Address): string {
+    return `Use the wallet ${subscriptionWallet.toLowerCase()} for the subscription of ${user.toLowerCase()} to the community ${communityName}`;
+}
+
 export class HTTPTransport {
+    // Token of the user session, sent with every request once user is logged in.
+    public sessionToken?: string;
+
     public constructor(public address: string) {
     }
 
@@ -22,7 +48,8 @@ export class HTTPTransport {
 
     // JSON RPC request
     async request(endpoint: string, request = null): Promise<any> {
-        const response = await Axios.post(endpoint, request).then(resp => {
+        const headers = this.sessionToken ? { Authorization: `Bearer ${this.sessionToken}` } : {};
+        const response = await Axios.post(endpoint, request, { headers }).then(resp => {
             return resp;
         });
 
@@ -49,6 +76,55 @@ export class Provider {
         return response.address;
     }
 
+    // Logs in with the main wallet of user. Session is required by the endpoints modifying the user data.
+    async login(ethSigner: ethers.Signer): Promise<LoginResponse> {
+        const address = await ethSigner.getAddress();
+
+        let endpoint = this.transport.endpoint("/auth/challenge");
+        const challenge: ChallengeResponse = await this.transport.request(endpoint, {
+            address
+        });
+        const signature = await ethSigner.signMessage(challenge.message);
+
+        endpoint = this.transport.endpoint("/auth/login");
+        const session: LoginResponse = await this.transport.request(endpoint, {
+            address,
+            nonce: challenge.nonce,
+            signature
+        });
+        this.transport.sessionToken = session.token;
+
+        return session;
+    }
+
+    async registerUser(username: string, address: zksync.types.Address, signature: string): Promise<User> {
+        let endpoint = this.transport.endpoint("/register_user");
+        return await this.transport.request(endpoint, {
+            username,
+            address,
+            signature
+        });
+    }
+
+    async user(user: string): Promise<User> {
+        let endpoint = this.transport.endpoint("/user");
+        return await this.transport.request(endpoint, {
+            user
+        });
+    }
+
+    async community(communityName: string): Promise<Community> {
+        let endpoint = this.transport.endpoint("/community");
+        return await this.transport.request(endpoint, {
+            communityName
+        });
+    }
+
+    async communities(filter: CommunityListFilter = {}): Promise<CommunityListResponse> {
+        let endpoint = this.transport.endpoint("/communities");
+        return await this.transport.request(endpoint, filter);
+    }
+
     async isUserSubscribed(user: string, communityName: string): Promise<SubscriptionCheckResponse> {
         let endpoint = this.transport.endpoint("/is_user_subscribed");
         return await this.transport.request(endpoint, {
@@ -76,13 +152,117 @@ export class Provider {
         return response.signature.zksyncSignature;
     }
 
-    async subscribe(user: string, communityName: string, subscriptionWallet: zksync.types.Address, txs: zksync.types.SubscriptionTx[]) {
+    async getMintingSignaturesBatch(requests: MintingSignatureRequest[]): Promise<BatchMintingSignatureItem[]> {
+        let endpoint = this.transport.endpoint("/get_minting_signatures_batch");
+        let response = await this.transport.request(endpoint, {
+            requests
+        });
+
+        return response.results;
+    }
+
+    async verifyMintingSignature(
+        communityName: string,
+        mintingTx: zksync.types.TransferFrom,
+        signature: zksync.types.Signature
+    ): Promise<MintingSignatureVerificationResponse> {
+        let endpoint = this.transport.endpoint("/verify_minting_signature");
+        return await this.transport.request(endpoint, {
+            communityName,
+            mintingTx,
+            signature: { zksyncSignature: signature }
+        });
+    }
+
+    async claimStatus(user: string, communityName: string): Promise<ClaimRoundStatus[]> {
+        let endpoint = this.transport.endpoint("/claim_status");
+        let response = await this.transport.request(endpoint, {
+            user,
+            communityName
+        });
+
+        return response.rounds;
+    }
+
+    // Returns the hash of the signing key generated for the custodial mint. User has to set it
+    // for the main wallet (via the `ChangePubKey` transaction) before requesting the custodial mint.
+    async registerSigningKey(user: string): Promise<string> {
+        let endpoint = this.transport.endpoint("/custodial/register_signing_key");
+        let response = await this.transport.request(endpoint, {
+            user
+        });
+
+        return response.pubKeyHash;
+    }
+
+    async custodialMint(user: string, communityName: string): Promise<string> {
+        let endpoint = this.transport.endpoint("/custodial/mint");
+        let response = await this.transport.request(endpoint, {
+            user,
+            communityName
+        });
+
+        return response.txHash;
+    }
+
+    async subscribe(
+        user: string,
+        communityName: string,
+        subscriptionWallet: zksync.types.Address,
+        subscriptionWalletSignature: string,
+        txs: zksync.types.SubscriptionTx[]
+    ) {
         let endpoint = this.transport.endpoint("/subscribe");
         await this.transport.request(endpoint, {
             user,
             communityName,
             subscriptionWallet,
+            subscriptionWalletSignature,
+            txs
+        });
+    }
+
+    async extendSubscription(user: string, communityName: string, txs: zksync.types.SubscriptionTx[]): Promise<ExtendSubscriptionResponse> {
+        let endpoint = this.transport.endpoint("/extend_subscription");
+        return await this.transport.request(endpoint, {
+            user,
+            communityName,
+            txs
+        });
+    }
+
+    async migrateSubscription(
+        user: string,
+        communityName: string,
+        subscriptionWallet: zksync.types.Address,
+        subscriptionWalletSignature: string,
+        txs: zksync.types.SubscriptionTx[]
+    ): Promise<MigrateSubscriptionResponse> {
+        let endpoint = this.transport.endpoint("/migrate_subscription");
+        return await this.transport.request(endpoint, {
+            user,
+            communityName,
+            subscriptionWallet,
+            subscriptionWalletSignature,
             txs
         });
     }
+
+    async purchaseItem(user: string, communityName: string, itemId: string, txHash: string): Promise<Purchase> {
+        let endpoint = this.transport.endpoint("/purchase_item");
+        return await this.transport.request(endpoint, {
+            user,
+            communityName,
+            itemId,
+            txHash
+        });
+    }
+
+    async entitlements(user: string, communityName: string): Promise<EntitlementsResponse> {
+        let endpoint = this.transport.endpoint("/entitlements");
+        return await this.transport.request(endpoint, {
+            user,
+            communityName
+        });
+    }
 }
\ No newline at end of file
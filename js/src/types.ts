@@ -8,6 +8,10 @@ export interface SubscriptionCheckResponse {
     startedAt?: string;
     // DateTime of the subscription period end.
     expiresAt?: string;
+    // Amount of the periods covered by the pre-signed txs which are not sent yet.
+    remainingPeriods: number;
+    // `true` if user has to sign more txs for the subscription to not lapse.
+    renewalNeeded: boolean;
 }
 
 export interface GenesisAddressResponse {
@@ -20,9 +24,171 @@ export interface GrantedTokensResponse {
     token: string;
     // Amount of tokens user can mint
     amount: number;
+    // Address of the community ERC-20 token, if the community is declared in the Service Provider
+    tokenAddress?: string;
 }
 
 export interface MintedSignatureResponse {
     // Signature for a minting transaction.
     signature: { zksyncSignature: zksync.types.Signature }
 }
+
+export interface MintingSignatureRequest {
+    // Address of the user's main wallet.
+    user: string;
+    // Name of the community.
+    communityName: string;
+    // Created, but not signed minting transaction.
+    mintingTx: zksync.types.TransferFrom;
+}
+
+export interface BatchMintingSignatureItem {
+    // Name of the community from the request item.
+    communityName: string;
+    // Signature for a minting transaction, if the item was correct.
+    signature?: { zksyncSignature: zksync.types.Signature };
+    // Description of the occurred error, if the item was incorrect.
+    errorDescription?: string;
+}
+
+export interface MintingSignatureVerificationResponse {
+    // `true` if the signature was issued by the Community Oracle for this transaction.
+    issuedByOracle: boolean;
+    // `true` if the transaction can be executed at the moment.
+    withinValidityWindow: boolean;
+    // `true` if the transaction was already successfully executed in zkSync.
+    executed: boolean;
+}
+
+export interface ClaimRoundStatus {
+    // Grant round in the `YYYY-MM` form.
+    round: string;
+    // Amount of tokens granted in this round.
+    amount: number;
+    // `true` if the minting signature was issued in this round.
+    signatureIssued: boolean;
+    // Hash of the signed minting transaction. Missing while the custodial mint is in progress.
+    txHash?: string;
+    // `true` if the signed minting transaction was executed in zkSync.
+    executed: boolean;
+}
+
+export interface SubscriptionPlan {
+    name: string;
+    // Price of the single subscription period, in the base units of the token.
+    price: number;
+    // Symbol of the token used for payments.
+    token: string;
+    periodDays: number;
+}
+
+export interface CatalogItem {
+    // Identifier of the item, unique within the community.
+    id: string;
+    name: string;
+    description: string;
+    // Price of the item, in whole community tokens (scaled by `token_decimals`).
+    price: number;
+}
+
+export interface HoldingTier {
+    // Name of the tier, unique within the community.
+    name: string;
+    // Minimum balance required for the tier, in whole community tokens (scaled by `token_decimals`).
+    min_balance: number;
+}
+
+export interface Community {
+    name: string;
+    erc20_token_name: string;
+    erc20_token_address: zksync.types.Address;
+    zksync_token_id: number;
+    // Number of decimals of the community token, used to convert item prices and holding tiers into the base units.
+    token_decimals: number;
+    description: string;
+    // URL of the community icon.
+    icon_url?: string;
+    // Prefix of the community domain used in the key derivation.
+    domain_prefix: string;
+    items: CatalogItem[];
+    holding_tiers: HoldingTier[];
+    version: number;
+    archived: boolean;
+    // Community domain, e.g. "reddit.com/r/rust".
+    domain: string;
+    plans: SubscriptionPlan[];
+}
+
+export interface CommunityListFilter {
+    // List only communities which name starts with the prefix (case-insensitive).
+    namePrefix?: string;
+    // List only communities with the token of the given symbol or address.
+    token?: string;
+    includeArchived?: boolean;
+    offset?: number;
+    limit?: number;
+}
+
+export interface CommunityListResponse {
+    communities: Community[];
+    // Total amount of communities matching the filter.
+    total: number;
+}
+
+export interface Purchase {
+    communityName: string;
+    itemId: string;
+    // Amount of whole tokens burnt for the item.
+    price: number;
+    txHash: string;
+    // DateTime of the purchase.
+    purchasedAt: string;
+}
+
+export type Entitlement =
+    | { type: "subscription"; expiresAt?: string }
+    | { type: "item"; itemId: string; quantity: number }
+    | { type: "holdingTier"; tier: string; minBalance: number };
+
+export interface EntitlementsResponse {
+    user: zksync.types.Address;
+    communityName: string;
+    entitlements: Entitlement[];
+}
+
+export interface User {
+    username: string;
+    address: zksync.types.Address;
+    // DateTime of the registration.
+    registeredAt: string;
+}
+
+export interface ChallengeResponse {
+    nonce: string;
+    // Message to be signed by the main wallet.
+    message: string;
+    // DateTime after which the challenge can't be used.
+    expiresAt: string;
+}
+
+export interface LoginResponse {
+    token: string;
+    // DateTime of the session end.
+    expiresAt: string;
+}
+
+export interface MigrateSubscriptionResponse {
+    // DateTime until which the subscription is paid by the previous subscription wallets.
+    carriedOverUntil?: string;
+}
+
+export interface ExtendSubscriptionResponse {
+    // Amount of the txs added to the subscription.
+    addedTxs: number;
+    // Amount of the txs which replaced the stored ones.
+    replacedTxs: number;
+    // Amount of the txs which were already stored.
+    skippedTxs: number;
+    // DateTime of the subscription period end, taking the new txs into account.
+    expiresAt?: string;
+}
@@ -5,7 +5,7 @@ import * as zksync from "zksync";
 import {ethers} from "ethers";
 import {parseEther} from "ethers/utils";
 import * as types from "../src/types";
-import { Provider as ServiceProvider } from "../src/provider";
+import { Provider as ServiceProvider, subscriptionWalletMessage } from "../src/provider";
 
 const SERVICE_PROVIDER_URL = "http://127.0.0.1:8080/";
 const COMMUNITY_NAME = "TestCommunity";
@@ -172,11 +172,22 @@ async function createSubscriptionWallet(userWallet: Wallet, communityName: strin
 
 async function subscribe(userWallet: Wallet, subscriptionWallet: Wallet, communityName: string) {
     const serviceProvider = new ServiceProvider(SERVICE_PROVIDER_URL);
+    // Subscribing requires a session of user.
+    await serviceProvider.login(userWallet.ethSigner);
 
     const months = 12;
     const subscriptionTxs = await userWallet.createSubscriptionTransactions(subscriptionWallet, months);
+    const subscriptionWalletSignature = await subscriptionWallet.ethSigner.signMessage(
+        subscriptionWalletMessage(userWallet.address(), communityName, subscriptionWallet.address())
+    );
 
-    await serviceProvider.subscribe(userWallet.address(), communityName, subscriptionWallet.address(), subscriptionTxs);
+    await serviceProvider.subscribe(
+        userWallet.address(),
+        communityName,
+        subscriptionWallet.address(),
+        subscriptionWalletSignature,
+        subscriptionTxs
+    );
 }
 
 (async () => {
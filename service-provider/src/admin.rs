@@ -0,0 +1,8 @@
+//! Authorization of the requests to the private (admin) API of the Service Provider.
+//!
+//! Admin API is served on a separate bind address, and every request to it must contain the
+//! `X-Api-Key` header with one of the configured API keys. Each key has a role, which
+//! determines the set of operations available to its holder. Rules are shared with the admin API
+//! of the Community Oracle, so the implementation is re-exported from the `community_oracle` crate.
+
+pub use community_oracle::admin::{Admin, AdminApiKey, AdminAuthenticator, AdminRole};
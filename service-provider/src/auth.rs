@@ -0,0 +1,267 @@
+//! Authentication of the users of the public API.
+//!
+//! User proves the ownership of the main wallet with the challenge/response login: Service Provider issues a
+//! random nonce, user signs the message containing it with the Ethereum key of the main wallet, and receives
+//! a short-lived session token. Requests to the user-scoped endpoints must contain this token in the
+//! `Authorization: Bearer <token>` header.
+
+use crate::{
+    config::AuthConfig,
+    responses::{ChallengeResponse, ErrorResponse, LoginResponse},
+    zksync::{is_signed_by, Address, PackedEthSignature},
+};
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+use thiserror::Error;
+
+pub const AUTHORIZATION_HEADER: &str = "Authorization";
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// Maximum amount of the pending login challenges for a single address.
+const MAX_CHALLENGES_PER_ADDRESS: usize = 5;
+/// Maximum amount of the pending login challenges in total. Challenges are issued to unauthenticated
+/// callers, so their amount must be bounded regardless of the addresses they are requested for.
+const MAX_CHALLENGES: usize = 10_000;
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Missing or malformed {} header", AUTHORIZATION_HEADER)]
+    MissingToken,
+    #[error("Session is unknown or expired")]
+    InvalidSession,
+    #[error("Login challenge is unknown or expired")]
+    InvalidChallenge,
+    #[error("Signature doesn't match the address")]
+    InvalidSignature,
+    #[error("Session belongs to another user")]
+    WrongUser,
+}
+
+/// Creates the message user signs to log in.
+pub fn login_message(address: Address, nonce: &str) -> String {
+    format!(
+        "Log in to the Reddit Service Provider with the address 0x{}. Nonce: {}",
+        hex::encode(address.as_ref()),
+        nonce
+    )
+}
+
+/// Creates a random token in the hexadecimal form.
+fn random_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    hex::encode(bytes)
+}
+
+/// Address along with the time after which it's not valid anymore.
+/// Used both for the login challenges and the sessions.
+#[derive(Debug, Clone, Copy)]
+struct Expiring {
+    address: Address,
+    expires_at: DateTime<Utc>,
+}
+
+impl Expiring {
+    fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+}
+
+/// Storage of the issued login challenges and sessions.
+#[derive(Debug, Clone)]
+pub struct SessionManager {
+    config: AuthConfig,
+    /// Login challenges, keyed by the nonce.
+    challenges: Arc<RwLock<HashMap<String, Expiring>>>,
+    /// Sessions, keyed by the token.
+    sessions: Arc<RwLock<HashMap<String, Expiring>>>,
+}
+
+impl SessionManager {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config,
+            challenges: Default::default(),
+            sessions: Default::default(),
+        }
+    }
+
+    /// Issues a new login challenge for the address. Once the amount of the pending challenges
+    /// (either for the address or in total) hits the limit, the oldest one is dropped.
+    pub fn challenge(&self, address: Address) -> ChallengeResponse {
+        let nonce = random_token();
+        let expires_at = Utc::now() + Duration::seconds(self.config.challenge_lifetime_secs);
+
+        let mut challenges = self.challenges.write().unwrap();
+        challenges.retain(|_, challenge| !challenge.is_expired());
+
+        let address_challenges = challenges
+            .values()
+            .filter(|challenge| challenge.address == address)
+            .count();
+        if address_challenges >= MAX_CHALLENGES_PER_ADDRESS {
+            Self::drop_oldest(&mut challenges, |challenge| challenge.address == address);
+        } else if challenges.len() >= MAX_CHALLENGES {
+            Self::drop_oldest(&mut challenges, |_| true);
+        }
+
+        challenges.insert(
+            nonce.clone(),
+            Expiring {
+                address,
+                expires_at,
+            },
+        );
+
+        ChallengeResponse {
+            message: login_message(address, &nonce),
+            nonce,
+            expires_at,
+        }
+    }
+
+    /// Removes the challenge with the earliest expiration time among the ones matching the filter.
+    fn drop_oldest(challenges: &mut HashMap<String, Expiring>, filter: impl Fn(&Expiring) -> bool) {
+        let oldest = challenges
+            .iter()
+            .filter(|(_, challenge)| filter(challenge))
+            .min_by_key(|(_, challenge)| challenge.expires_at)
+            .map(|(nonce, _)| nonce.clone());
+
+        if let Some(nonce) = oldest {
+            challenges.remove(&nonce);
+        }
+    }
+
+    /// Checks the signed challenge and opens a new session for the address.
+    /// Every challenge can be used only once.
+    pub fn login(
+        &self,
+        address: Address,
+        nonce: &str,
+        signature: &PackedEthSignature,
+    ) -> Result<LoginResponse, AuthError> {
+        let challenge = self
+            .challenges
+            .write()
+            .unwrap()
+            .remove(nonce)
+            .ok_or(AuthError::InvalidChallenge)?;
+        if challenge.is_expired() || challenge.address != address {
+            return Err(AuthError::InvalidChallenge);
+        }
+
+        if !is_signed_by(&login_message(address, nonce), signature, address) {
+            return Err(AuthError::InvalidSignature);
+        }
+
+        let token = random_token();
+        let expires_at = Utc::now() + Duration::seconds(self.config.session_lifetime_secs);
+
+        let mut sessions = self.sessions.write().unwrap();
+        sessions.retain(|_, session| !session.is_expired());
+        sessions.insert(
+            token.clone(),
+            Expiring {
+                address,
+                expires_at,
+            },
+        );
+
+        Ok(LoginResponse { token, expires_at })
+    }
+
+    /// Returns the address of the user which session token is provided in the request.
+    pub fn session_address(&self, req: &HttpRequest) -> Result<Address, AuthError> {
+        let token = req
+            .headers()
+            .get(AUTHORIZATION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| value.starts_with(BEARER_PREFIX))
+            .map(|value| value[BEARER_PREFIX.len()..].trim())
+            .ok_or(AuthError::MissingToken)?;
+
+        match self.sessions.read().unwrap().get(token) {
+            Some(session) if !session.is_expired() => Ok(session.address),
+            _ => Err(AuthError::InvalidSession),
+        }
+    }
+}
+
+/// Extractor for the user-scoped requests: checks the session token and decodes the JSON body of the request.
+/// Unlike the `Admin` extractor, it doesn't reject the request by itself, since the user the request is
+/// related to is only known after the request is decoded.
+#[derive(Debug)]
+pub struct Authenticated<T> {
+    session: Result<Address, AuthError>,
+    public_status_checks: bool,
+    request: T,
+}
+
+impl<T> Authenticated<T> {
+    /// Returns the request without checking the session.
+    pub fn request(&self) -> &T {
+        &self.request
+    }
+
+    /// Returns the request if it's sent within the session of the user, and the error response otherwise.
+    /// Must be used by the endpoints which modify the user data.
+    pub fn authorize(self, user: Address) -> Result<T, HttpResponse> {
+        let error = match self.session {
+            Ok(address) if address == user => return Ok(self.request),
+            Ok(_) => AuthError::WrongUser,
+            Err(error) => error,
+        };
+
+        log::warn!("Rejected user request: {}", error);
+        let response = match error {
+            AuthError::WrongUser => HttpResponse::Forbidden(),
+            _ => HttpResponse::Unauthorized(),
+        }
+        .json(ErrorResponse::error(&error.to_string()));
+
+        Err(response)
+    }
+
+    /// Same as `authorize`, but the session isn't required if the status checks are configured to be public.
+    /// Must be used by the read-only endpoints.
+    pub fn authorize_status_check(self, user: Address) -> Result<T, HttpResponse> {
+        if self.public_status_checks {
+            Ok(self.request)
+        } else {
+            self.authorize(user)
+        }
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Authenticated<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let sessions = req
+            .app_data::<web::Data<SessionManager>>()
+            .expect("SessionManager is not registered");
+        let session = sessions.session_address(req);
+        let public_status_checks = sessions.config.public_status_checks;
+        let request = web::Json::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let request = request.await?.into_inner();
+
+            Ok(Authenticated {
+                session,
+                public_status_checks,
+                request,
+            })
+        })
+    }
+}
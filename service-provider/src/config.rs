@@ -1,15 +1,234 @@
-use crate::zksync::Address;
+use crate::{admin::AdminApiKey, zksync::Address};
+use community_oracle::config::AppConfig as OracleConfig;
 use serde_derive::Deserialize;
 use std::env;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     pub app_bind_address: String,
+    /// Address on which the private (admin) API is served. Must not be exposed to the public.
+    pub admin_bind_address: String,
+    /// API keys allowed to access the admin API.
+    #[serde(default)]
+    pub admin_api_keys: Vec<AdminApiKey>,
     pub zksync_rest_api_address: String,
     pub zksync_json_rpc_address: String,
-    pub community_oracle_address: String,
+    /// Addresses of the Community Oracle instances. Requests are distributed among the
+    /// available instances in the round-robin manner. Not used in the embedded oracle mode.
+    #[serde(default)]
+    pub community_oracle_addresses: Vec<String>,
+    /// Secret shared with the Community Oracle, used to authenticate requests.
+    /// Required unless the oracle is embedded.
+    #[serde(default)]
+    pub community_oracle_api_secret: Option<String>,
     pub burn_account_address: Address,
+    /// Settings of the interaction with the Community Oracle and zkSync server.
+    #[serde(default)]
+    pub resilience: ResilienceConfig,
+    /// Settings of the user authentication.
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Settings of the subscription event notifications.
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// If set, Community Oracle is hosted by the Service Provider in-process with the provided config,
+    /// instead of being accessed through the HTTP API.
+    #[serde(default)]
+    pub embedded_oracle: Option<OracleConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ResilienceConfig {
+    /// Timeout for a single request, in milliseconds.
+    pub request_timeout_ms: u64,
+    /// Maximum amount of retries for the requests that are safe to repeat.
+    pub max_retries: u32,
+    /// Delay before the first retry, in milliseconds. Every next delay is twice as long.
+    pub retry_base_delay_ms: u64,
+    /// Amount of consecutive failures after which the service is considered down.
+    pub circuit_breaker_threshold: u32,
+    /// Time after which the service that is considered down is checked again, in milliseconds.
+    pub circuit_breaker_reset_ms: u64,
+    /// Interval between the health probes of every Community Oracle instance, in milliseconds.
+    pub health_check_interval_ms: u64,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_ms: 10_000,
+            max_retries: 3,
+            retry_base_delay_ms: 200,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_reset_ms: 30_000,
+            health_check_interval_ms: 10_000,
+        }
+    }
+}
+
+impl ResilienceConfig {
+    /// Loads config from env. Variables that are not set are replaced with the default values.
+    fn load_from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            request_timeout_ms: env_or("REQUEST_TIMEOUT_MS", default.request_timeout_ms),
+            max_retries: env_or("MAX_RETRIES", default.max_retries),
+            retry_base_delay_ms: env_or("RETRY_BASE_DELAY_MS", default.retry_base_delay_ms),
+            circuit_breaker_threshold: env_or(
+                "CIRCUIT_BREAKER_THRESHOLD",
+                default.circuit_breaker_threshold,
+            ),
+            circuit_breaker_reset_ms: env_or(
+                "CIRCUIT_BREAKER_RESET_MS",
+                default.circuit_breaker_reset_ms,
+            ),
+            health_check_interval_ms: env_or(
+                "HEALTH_CHECK_INTERVAL_MS",
+                default.health_check_interval_ms,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Time during which the login challenge can be signed, in seconds.
+    pub challenge_lifetime_secs: i64,
+    /// Lifetime of the session, in seconds.
+    pub session_lifetime_secs: i64,
+    /// If `true`, read-only status checks (e.g. `/is_user_subscribed`) don't require a session.
+    pub public_status_checks: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            challenge_lifetime_secs: 300,
+            session_lifetime_secs: 3600,
+            public_status_checks: true,
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Loads config from env. Variables that are not set are replaced with the default values.
+    fn load_from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            challenge_lifetime_secs: env_or(
+                "CHALLENGE_LIFETIME_SECS",
+                default.challenge_lifetime_secs,
+            ),
+            session_lifetime_secs: env_or("SESSION_LIFETIME_SECS", default.session_lifetime_secs),
+            public_status_checks: env_or("PUBLIC_STATUS_CHECKS", default.public_status_checks),
+        }
+    }
+}
+
+/// Destination of the subscription events.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventSinkConfig {
+    /// Events are written to the application log.
+    Log,
+    /// Events are sent in the POST requests to the URL, signed with the secret.
+    Webhook { url: String, secret: String },
+}
+
+impl FromStr for EventSinkConfig {
+    type Err = String;
+
+    /// Parses the sink in the `log` or `webhook:<secret>:<url>` form.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':').map(str::trim);
+        match (parts.next().unwrap_or_default(), parts.next(), parts.next()) {
+            ("log", None, None) => Ok(Self::Log),
+            ("webhook", Some(secret), Some(url)) if !secret.is_empty() && !url.is_empty() => {
+                Ok(Self::Webhook {
+                    url: url.to_string(),
+                    secret: secret.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Event sink must be in the `log` or `webhook:<secret>:<url>` form, got {}",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Subscription is considered to need renewal once its remaining pre-signed txs cover fewer periods than this.
+    pub renewal_threshold_periods: usize,
+    /// Interval between the checks of the subscriptions that need renewal, in seconds.
+    pub renewal_check_interval_secs: u64,
+    /// Destinations of the subscription events.
+    pub sinks: Vec<EventSinkConfig>,
+    /// Maximum amount of attempts to deliver the event to a webhook, after which delivery is marked as failed.
+    pub webhook_max_attempts: u32,
+    /// Delay before the first retry of the webhook delivery, in seconds. Every next delay is twice as long.
+    pub webhook_retry_base_delay_secs: u64,
+    /// Interval between the checks of the pending webhook deliveries, in seconds.
+    pub webhook_poll_interval_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            renewal_threshold_periods: 1,
+            renewal_check_interval_secs: 3600,
+            sinks: vec![EventSinkConfig::Log],
+            webhook_max_attempts: 8,
+            webhook_retry_base_delay_secs: 30,
+            webhook_poll_interval_secs: 5,
+        }
+    }
+}
+
+impl NotificationsConfig {
+    /// Loads config from env. Variables that are not set are replaced with the default values.
+    fn load_from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            renewal_threshold_periods: env_or(
+                "RENEWAL_THRESHOLD_PERIODS",
+                default.renewal_threshold_periods,
+            ),
+            renewal_check_interval_secs: env_or(
+                "RENEWAL_CHECK_INTERVAL_SECS",
+                default.renewal_check_interval_secs,
+            ),
+            sinks: match env::var("EVENT_SINKS") {
+                Ok(sinks) => sinks
+                    .split(',')
+                    .filter(|sink| !sink.trim().is_empty())
+                    .map(|sink| {
+                        sink.parse()
+                            .unwrap_or_else(|err| panic!("Can't decode EVENT_SINKS: {}", err))
+                    })
+                    .collect(),
+                Err(_) => default.sinks,
+            },
+            webhook_max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", default.webhook_max_attempts),
+            webhook_retry_base_delay_secs: env_or(
+                "WEBHOOK_RETRY_BASE_DELAY_SECS",
+                default.webhook_retry_base_delay_secs,
+            ),
+            webhook_poll_interval_secs: env_or(
+                "WEBHOOK_POLL_INTERVAL_SECS",
+                default.webhook_poll_interval_secs,
+            ),
+        }
+    }
 }
 
 impl AppConfig {
@@ -26,20 +245,57 @@ impl AppConfig {
     fn load_from_env() -> Self {
         Self {
             app_bind_address: env::var("APP_BIND_ADDRESS").expect("APP_BIND_ADDRESS"),
+            admin_bind_address: env::var("ADMIN_BIND_ADDRESS").expect("ADMIN_BIND_ADDRESS"),
+            admin_api_keys: env::var("ADMIN_API_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|key| !key.trim().is_empty())
+                .map(|key| {
+                    key.parse()
+                        .unwrap_or_else(|err| panic!("Can't decode ADMIN_API_KEYS: {}", err))
+                })
+                .collect(),
             zksync_rest_api_address: env::var("ZKSYNC_REST_API_ADDRESS")
                 .expect("ZKSYNC_REST_API_ADDRESS"),
             zksync_json_rpc_address: env::var("ZKSYNC_JSON_RPC_ADDRESS")
                 .expect("ZKSYNC_JSON_RPC_ADDRESS"),
-            community_oracle_address: env::var("COMMUNITY_ORACLE_ADDRESS")
-                .expect("COMMUNITY_ORACLE_ADDRESS"),
+            community_oracle_addresses: env::var("COMMUNITY_ORACLE_ADDRESSES")
+                .unwrap_or_default()
+                .split(',')
+                .map(|address| address.trim().to_string())
+                .filter(|address| !address.is_empty())
+                .collect(),
+            community_oracle_api_secret: env::var("COMMUNITY_ORACLE_API_SECRET").ok(),
             burn_account_address: env::var("BURN_ACCOUNT_ADDRESS")
                 .expect("BURN_ACCOUNT_ADDRESS")
                 .parse()
                 .expect("Can't decode burn account address"),
+            resilience: ResilienceConfig::load_from_env(),
+            auth: AuthConfig::load_from_env(),
+            notifications: NotificationsConfig::load_from_env(),
+            embedded_oracle: if env_or("EMBEDDED_ORACLE", false) {
+                Some(OracleConfig::load(true, &PathBuf::new()))
+            } else {
+                None
+            },
         }
     }
 }
 
+/// Reads the optional variable from env, returning the default value if it's not set.
+fn env_or<T>(name: &str, default: T) -> T
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Debug,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|err| panic!("Can't decode {}: {:?}", name, err)),
+        Err(_) => default,
+    }
+}
+
 fn load_json<T: serde::de::DeserializeOwned>(filepath: &PathBuf) -> T {
     let buffer = std::fs::read_to_string(filepath).expect("Failed to read the test spec file");
     serde_json::from_str(&buffer).expect(
@@ -1,11 +1,15 @@
 use crate::{
-    database::{Community, DatabaseAccess, Subscription},
+    database::{
+        Community, CommunityError, CommunityFilter, DatabaseAccess, DeliveryStatus, Purchase,
+        PurchaseError, Subscription, User, UserError, WebhookDelivery, WebhookError,
+    },
     zksync::{Address, SubscriptionTx},
 };
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 use thiserror::Error;
@@ -18,18 +22,34 @@ pub enum MemoryDbError {
     DifferrentSubscriptionWallet,
 }
 
+/// Outbox of the subscription events.
+#[derive(Debug, Default)]
+struct Outbox {
+    /// Keys of all the recorded events.
+    event_keys: HashSet<String>,
+    /// Webhook deliveries, ordered by their identifiers (which start from 1).
+    deliveries: Vec<WebhookDelivery>,
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryDb {
     communities: Arc<RwLock<HashMap<String, Community>>>,
     subscriptions: Arc<RwLock<HashMap<Address, Vec<Subscription>>>>,
+    purchases: Arc<RwLock<HashMap<Address, Vec<Purchase>>>>,
+    /// Hashes of the burn txs used for the purchases. Like the rest of the data, they are lost on restart,
+    /// after which any of these txs can be redeemed again.
+    redeemed_txs: Arc<RwLock<HashSet<String>>>,
+    /// Registered users, keyed by the lowercase username.
+    users: Arc<RwLock<HashMap<String, User>>>,
+    outbox: Arc<RwLock<Outbox>>,
 }
 
 impl MemoryDb {
     /// Finds a user subscription object given the user's address and community name, and
     /// applies the mutating function to this object.
-    fn modify_subscription<F>(&self, address: Address, community: &str, f: F) -> Result<()>
+    fn modify_subscription<F, T>(&self, address: Address, community: &str, f: F) -> Result<T>
     where
-        F: FnOnce(&mut Subscription),
+        F: FnOnce(&mut Subscription) -> T,
     {
         let mut existing_subscriptions = self.subscriptions.write().unwrap();
 
@@ -42,9 +62,25 @@ impl MemoryDb {
             .find(|sub| sub.service_name == community)
             .ok_or(MemoryDbError::UserIsNotSubscribed)?;
 
-        f(subscription);
+        Ok(f(subscription))
+    }
 
-        Ok(())
+    /// Finds a community given its name, and applies the mutating function to it.
+    /// Version of the community is incremented after the modification.
+    fn modify_community<F>(&self, community_name: &str, f: F) -> Result<Community>
+    where
+        F: FnOnce(&mut Community) -> Result<()>,
+    {
+        let mut communities = self.communities.write().unwrap();
+
+        let community = communities
+            .get_mut(community_name)
+            .ok_or(CommunityError::Unknown)?;
+
+        f(community)?;
+        community.version += 1;
+
+        Ok(community.clone())
     }
 }
 
@@ -56,14 +92,89 @@ impl DatabaseAccess for MemoryDb {
         Ok(Self {
             communities: Default::default(),
             subscriptions: Default::default(),
+            purchases: Default::default(),
+            redeemed_txs: Default::default(),
+            users: Default::default(),
+            outbox: Default::default(),
         })
     }
 
-    async fn declare_community(&self, community: Community) -> Result<()> {
+    async fn declare_community(&self, mut community: Community) -> Result<Community> {
         let mut communities = self.communities.write().unwrap();
-        communities.insert(community.name.clone(), community);
 
-        Ok(())
+        if communities.contains_key(&community.name) {
+            return Err(CommunityError::AlreadyDeclared.into());
+        }
+
+        community.version = 1;
+        community.archived = false;
+        communities.insert(community.name.clone(), community.clone());
+
+        Ok(community)
+    }
+
+    async fn update_community(&self, community: Community) -> Result<Community> {
+        let community_name = community.name.clone();
+
+        self.modify_community(&community_name, move |stored| {
+            if stored.version != community.version {
+                return Err(CommunityError::VersionMismatch {
+                    expected: community.version,
+                    actual: stored.version,
+                }
+                .into());
+            }
+
+            // Archival state is changed through the dedicated method only.
+            let archived = stored.archived;
+            *stored = community;
+            stored.archived = archived;
+
+            Ok(())
+        })
+    }
+
+    async fn archive_community(&self, community_name: &str) -> Result<Community> {
+        self.modify_community(community_name, |stored| {
+            stored.archived = true;
+
+            Ok(())
+        })
+    }
+
+    async fn delete_community(&self, community_name: &str) -> Result<Vec<(Address, Subscription)>> {
+        let mut communities = self.communities.write().unwrap();
+        if !communities.contains_key(community_name) {
+            return Err(CommunityError::Unknown.into());
+        }
+
+        // Subscriptions are checked under the same lock they are removed with, so a subscription
+        // added after the caller's check can't be dropped silently.
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        let now = Utc::now();
+        let has_active_subscribers = subscriptions
+            .values()
+            .flatten()
+            .any(|sub| sub.service_name == community_name && sub.is_active(now));
+        if has_active_subscribers {
+            return Err(CommunityError::HasActiveSubscribers.into());
+        }
+
+        communities.remove(community_name);
+        let mut removed = Vec::new();
+        for (address, user_subscriptions) in subscriptions.iter_mut() {
+            let (community_subscriptions, other_subscriptions) = user_subscriptions
+                .drain(..)
+                .partition(|sub| sub.service_name == community_name);
+            *user_subscriptions = other_subscriptions;
+            removed.extend(
+                community_subscriptions
+                    .into_iter()
+                    .map(|sub: Subscription| (*address, sub)),
+            );
+        }
+
+        Ok(removed)
     }
 
     async fn get_community(&self, community_name: &str) -> Result<Option<Community>> {
@@ -72,6 +183,66 @@ impl DatabaseAccess for MemoryDb {
         Ok(communities.get(community_name).cloned())
     }
 
+    async fn get_communities(&self) -> Result<Vec<Community>> {
+        let communities = self.communities.read().unwrap();
+
+        Ok(communities.values().cloned().collect())
+    }
+
+    async fn list_communities(&self, filter: &CommunityFilter) -> Result<(Vec<Community>, usize)> {
+        let communities = self.communities.read().unwrap();
+
+        let mut matching: Vec<_> = communities
+            .values()
+            .filter(|community| filter.matches(community))
+            .collect();
+        matching.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let total = matching.len();
+        let page = matching
+            .into_iter()
+            .skip(filter.offset)
+            .take(filter.limit)
+            .cloned()
+            .collect();
+
+        Ok((page, total))
+    }
+
+    async fn get_community_subscriptions(
+        &self,
+        community_name: &str,
+    ) -> Result<Vec<(Address, Subscription)>> {
+        let subscriptions = self.subscriptions.read().unwrap();
+
+        let community_subscriptions = subscriptions
+            .iter()
+            .flat_map(|(address, user_subscriptions)| {
+                user_subscriptions
+                    .iter()
+                    .filter(|sub| sub.service_name == community_name)
+                    .map(move |sub| (*address, sub.clone()))
+            })
+            .collect();
+
+        Ok(community_subscriptions)
+    }
+
+    async fn get_subscriptions(&self) -> Result<Vec<(Address, Subscription)>> {
+        let subscriptions = self.subscriptions.read().unwrap();
+
+        let all_subscriptions = subscriptions
+            .iter()
+            .flat_map(|(address, user_subscriptions)| {
+                user_subscriptions
+                    .iter()
+                    .map(move |sub| (*address, sub.clone()))
+            })
+            .collect();
+
+        Ok(all_subscriptions)
+    }
+
     async fn add_subscription(&self, address: Address, subscription: Subscription) -> Result<()> {
         let mut existing_subscriptions = self.subscriptions.write().unwrap();
 
@@ -103,11 +274,144 @@ impl DatabaseAccess for MemoryDb {
         community: &str,
         txs: Vec<SubscriptionTx>,
     ) -> Result<()> {
-        self.modify_subscription(address, community, |sub| sub.add_subscription_txs(txs))?;
+        self.modify_subscription(address, community, |sub| sub.add_subscription_txs(txs))??;
+
+        Ok(())
+    }
+
+    async fn migrate_subscription(
+        &self,
+        address: Address,
+        community: &str,
+        new_wallet: Address,
+        paid_until: Option<DateTime<Utc>>,
+        txs: Vec<SubscriptionTx>,
+    ) -> Result<()> {
+        self.modify_subscription(address, community, |sub| {
+            sub.migrate(new_wallet, paid_until, txs)
+        })?;
+
+        Ok(())
+    }
+
+    async fn add_event(&self, event_key: &str, deliveries: Vec<WebhookDelivery>) -> Result<bool> {
+        let mut outbox = self.outbox.write().unwrap();
+        if !outbox.event_keys.insert(event_key.to_string()) {
+            return Ok(false);
+        }
+
+        for mut delivery in deliveries {
+            delivery.id = outbox.deliveries.len() as u64 + 1;
+            outbox.deliveries.push(delivery);
+        }
+
+        Ok(true)
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let outbox = self.outbox.read().unwrap();
+
+        let deliveries = outbox
+            .deliveries
+            .iter()
+            .filter(|delivery| status.map_or(true, |status| delivery.status == status))
+            .cloned()
+            .collect();
+
+        Ok(deliveries)
+    }
+
+    async fn update_webhook_delivery(&self, delivery: WebhookDelivery) -> Result<()> {
+        let mut outbox = self.outbox.write().unwrap();
+
+        let stored = outbox
+            .deliveries
+            .get_mut((delivery.id as usize).wrapping_sub(1))
+            .ok_or(WebhookError::UnknownDelivery(delivery.id))?;
+        *stored = delivery;
+
+        Ok(())
+    }
+
+    async fn replay_webhook_deliveries(&self, ids: &[u64]) -> Result<Vec<WebhookDelivery>> {
+        let mut outbox = self.outbox.write().unwrap();
+
+        if let Some(&id) = ids
+            .iter()
+            .find(|&&id| id == 0 || id as usize > outbox.deliveries.len())
+        {
+            return Err(WebhookError::UnknownDelivery(id).into());
+        }
+
+        let replayed = ids
+            .iter()
+            .map(|&id| {
+                let delivery = &mut outbox.deliveries[id as usize - 1];
+                delivery.replay();
+                delivery.clone()
+            })
+            .collect();
+
+        Ok(replayed)
+    }
+
+    async fn add_purchase(&self, address: Address, purchase: Purchase) -> Result<()> {
+        // Check and mark the transaction as redeemed under the same lock, so that concurrent requests
+        // can't redeem it twice.
+        let mut redeemed_txs = self.redeemed_txs.write().unwrap();
+        if !redeemed_txs.insert(purchase.tx_hash.clone()) {
+            return Err(PurchaseError::TxAlreadyRedeemed(purchase.tx_hash).into());
+        }
+
+        let mut purchases = self.purchases.write().unwrap();
+        purchases.entry(address).or_default().push(purchase);
 
         Ok(())
     }
 
+    async fn get_user_purchases(&self, address: Address) -> Result<Vec<Purchase>> {
+        let purchases = self.purchases.read().unwrap();
+
+        Ok(purchases.get(&address).cloned().unwrap_or_default())
+    }
+
+    async fn register_user(&self, user: User) -> Result<User> {
+        let mut users = self.users.write().unwrap();
+
+        let key = user.username.to_lowercase();
+        if let Some(existing) = users.get(&key) {
+            if existing.address != user.address {
+                return Err(UserError::UsernameTaken.into());
+            }
+            return Ok(existing.clone());
+        }
+        if users
+            .values()
+            .any(|existing| existing.address == user.address)
+        {
+            return Err(UserError::AddressTaken.into());
+        }
+
+        users.insert(key, user.clone());
+
+        Ok(user)
+    }
+
+    async fn get_user_by_name(&self, username: &str) -> Result<Option<User>> {
+        let users = self.users.read().unwrap();
+
+        Ok(users.get(&username.to_lowercase()).cloned())
+    }
+
+    async fn get_user_by_address(&self, address: Address) -> Result<Option<User>> {
+        let users = self.users.read().unwrap();
+
+        Ok(users.values().find(|user| user.address == address).cloned())
+    }
+
     async fn get_user_subscriptions(&self, address: Address) -> Result<Vec<Subscription>> {
         let existing_subscriptions = self.subscriptions.read().unwrap();
 
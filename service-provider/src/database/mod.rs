@@ -9,27 +9,115 @@
 use crate::zksync::{Address, SubscriptionTx};
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
 
 pub use self::{
     memory_db::MemoryDb,
-    types::{Community, Subscription},
+    types::{
+        Community, CommunityFilter, DeliveryStatus, Purchase, Subscription, User, WebhookDelivery,
+    },
 };
 
 pub mod memory_db;
 pub mod types;
 
+/// Errors of the community management operations. Back-ends must report them as-is
+/// (wrapped into `anyhow::Error`), so they can be distinguished from the internal errors.
+#[derive(Error, Debug)]
+pub enum CommunityError {
+    #[error("Community is already declared")]
+    AlreadyDeclared,
+    #[error("Community is not declared")]
+    Unknown,
+    #[error(
+        "Community was modified concurrently: expected version {expected}, actual version {actual}"
+    )]
+    VersionMismatch { expected: u64, actual: u64 },
+    #[error("Incorrect community data: {0}")]
+    InvalidData(String),
+    #[error("Community has active subscribers")]
+    HasActiveSubscribers,
+}
+
+/// Errors of the item purchase operations. Same as `CommunityError`, they must be reported as-is.
+#[derive(Error, Debug)]
+pub enum PurchaseError {
+    #[error("Transaction {0} has already been redeemed")]
+    TxAlreadyRedeemed(String),
+}
+
+/// Errors of the subscription operations. Same as `CommunityError`, they must be reported as-is.
+#[derive(Error, Debug)]
+pub enum SubscriptionError {
+    #[error(
+        "Tx for the period starting at {0} may have been already sent, so it can't be replaced"
+    )]
+    TxAlreadySent(DateTime<Utc>),
+}
+
+/// Errors of the user registry operations. Same as `CommunityError`, they must be reported as-is.
+#[derive(Error, Debug)]
+pub enum UserError {
+    #[error("Username is incorrect")]
+    InvalidUsername,
+    #[error("Username is already registered for another address")]
+    UsernameTaken,
+    #[error("Address is already linked to another username")]
+    AddressTaken,
+    #[error("User is not registered")]
+    Unknown,
+}
+
+/// Errors of the webhook outbox operations. Same as `CommunityError`, they must be reported as-is.
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Webhook delivery {0} is not found")]
+    UnknownDelivery(u64),
+}
+
 #[async_trait]
 pub trait DatabaseAccess: Sized {
     type DatabaseInitParams;
 
     fn init(params: Self::DatabaseInitParams) -> Result<Self>;
 
-    async fn declare_community(&self, community: Community) -> Result<()>;
+    /// Stores a new community with the initial version. Fails if community is already declared.
+    async fn declare_community(&self, community: Community) -> Result<Community>;
+
+    /// Replaces the community data, if the stored version matches the version of the provided community.
+    /// Returns the updated community with the incremented version.
+    async fn update_community(&self, community: Community) -> Result<Community>;
+
+    /// Marks the community as archived. Returns the updated community.
+    async fn archive_community(&self, community_name: &str) -> Result<Community>;
+
+    /// Removes the community along with all its subscriptions. Fails if any of the subscriptions is active
+    /// (see `Subscription::is_active`), which must be checked atomically with the removal.
+    /// Returns the removed subscriptions.
+    async fn delete_community(&self, community_name: &str) -> Result<Vec<(Address, Subscription)>>;
 
     async fn get_community(&self, community_name: &str) -> Result<Option<Community>>;
 
+    async fn get_communities(&self) -> Result<Vec<Community>>;
+
+    /// Returns the page of communities matching the filter (ordered by name),
+    /// along with the total amount of matching communities.
+    async fn list_communities(&self, filter: &CommunityFilter) -> Result<(Vec<Community>, usize)>;
+
+    /// Returns the subscriptions to the community along with the addresses of subscribed users.
+    async fn get_community_subscriptions(
+        &self,
+        community_name: &str,
+    ) -> Result<Vec<(Address, Subscription)>>;
+
+    /// Returns all the subscriptions along with the addresses of subscribed users, including the subscriptions
+    /// to the communities not declared in the Service Provider (e.g. the built-in communities of the oracle).
+    async fn get_subscriptions(&self) -> Result<Vec<(Address, Subscription)>>;
+
     async fn add_subscription(&self, address: Address, subscription: Subscription) -> Result<()>;
 
+    /// Adds the pre-signed txs to the subscription, see `Subscription::add_subscription_txs`.
     async fn add_subscription_txs(
         &self,
         address: Address,
@@ -37,6 +125,47 @@ pub trait DatabaseAccess: Sized {
         txs: Vec<SubscriptionTx>,
     ) -> Result<()>;
 
+    /// Records the item purchase. Fails if the purchase transaction has already been redeemed.
+    async fn add_purchase(&self, address: Address, purchase: Purchase) -> Result<()>;
+
+    async fn get_user_purchases(&self, address: Address) -> Result<Vec<Purchase>>;
+
+    /// Links the username to the address. Registering the same pair again has no effect, but both
+    /// the username and the address can be linked only once.
+    async fn register_user(&self, user: User) -> Result<User>;
+
+    /// Looks up the user by the username (case-insensitive).
+    async fn get_user_by_name(&self, username: &str) -> Result<Option<User>>;
+
+    async fn get_user_by_address(&self, address: Address) -> Result<Option<User>>;
+
+    /// Replaces the wallet of the existing subscription, see `Subscription::migrate`.
+    async fn migrate_subscription(
+        &self,
+        address: Address,
+        community: &str,
+        new_wallet: Address,
+        paid_until: Option<DateTime<Utc>>,
+        txs: Vec<SubscriptionTx>,
+    ) -> Result<()>;
+
+    /// Records the event in the outbox along with its webhook deliveries. Recording the event with
+    /// the same key again has no effect. Returns `false` if the event was already recorded.
+    async fn add_event(&self, event_key: &str, deliveries: Vec<WebhookDelivery>) -> Result<bool>;
+
+    /// Returns the webhook deliveries with the given status (or all of them), from the oldest to the newest.
+    async fn get_webhook_deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+    ) -> Result<Vec<WebhookDelivery>>;
+
+    /// Stores the result of the delivery attempt.
+    async fn update_webhook_delivery(&self, delivery: WebhookDelivery) -> Result<()>;
+
+    /// Schedules the deliveries to be attempted again, see `WebhookDelivery::replay`.
+    /// Fails without modifying the outbox if any of the deliveries is unknown.
+    async fn replay_webhook_deliveries(&self, ids: &[u64]) -> Result<Vec<WebhookDelivery>>;
+
     async fn get_user_subscriptions(&self, address: Address) -> Result<Vec<Subscription>>;
 
     async fn get_subscription(
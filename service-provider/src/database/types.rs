@@ -1,11 +1,234 @@
-use crate::zksync::{Address, SubscriptionTx};
+use crate::{
+    database::{CommunityError, SubscriptionError},
+    notifications::SubscriptionEvent,
+    zksync::{Address, SubscriptionTx, SubscriptionTxExt, SUBSCRIPTION_PERIOD_DAYS},
+};
+use chrono::{DateTime, Duration, Utc};
+use community_oracle::responses::CommunityDescription;
+use num::BigUint;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Default prefix of the community domain, see `Community::domain`.
+pub const DEFAULT_DOMAIN_PREFIX: &str = "reddit.com/r";
+/// Default number of decimals of the community token.
+pub const DEFAULT_TOKEN_DECIMALS: u8 = 18;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Community {
     pub name: String,
+    /// Symbol of the community token, the same in Ethereum and zkSync.
     pub erc20_token_name: String,
     pub erc20_token_address: Address,
+    /// ID of the community token in the zkSync network.
+    pub zksync_token_id: u16,
+    /// Number of decimals of the community token, used to convert the amounts of the whole tokens
+    /// (item prices, holding tiers) into the base units.
+    #[serde(default = "default_token_decimals")]
+    pub token_decimals: u8,
+    #[serde(default)]
+    pub description: String,
+    /// URL of the community icon, shown by clients. Must be an HTTP(S) URL.
+    pub icon_url: Option<String>,
+    /// Prefix of the community domain (e.g. `reddit.com/r`), used to derive the community-specific keys.
+    #[serde(default = "default_domain_prefix")]
+    pub domain_prefix: String,
+    /// Items which can be bought for the community tokens.
+    #[serde(default)]
+    pub items: Vec<CatalogItem>,
+    /// Tiers granted to users holding the certain amount of the community tokens.
+    #[serde(default)]
+    pub holding_tiers: Vec<HoldingTier>,
+    /// Version of the community data, incremented on every change.
+    /// Used to detect concurrent modifications (see `DatabaseAccess::update_community`).
+    #[serde(default)]
+    pub version: u64,
+    /// Archived community accepts no new subscriptions, while existing ones are honored until they run out.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// Item which can be bought by burning the community tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogItem {
+    /// Identifier of the item, unique within the community.
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Price of the item, in whole community tokens (see `Community::token_base_units`).
+    pub price: u64,
+}
+
+/// Tier granted to users which balance of the community tokens is not less than `min_balance`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldingTier {
+    /// Name of the tier, unique within the community.
+    pub name: String,
+    /// Minimum balance, in whole community tokens (see `Community::token_base_units`).
+    pub min_balance: u64,
+}
+
+fn default_token_decimals() -> u8 {
+    DEFAULT_TOKEN_DECIMALS
+}
+
+fn default_domain_prefix() -> String {
+    DEFAULT_DOMAIN_PREFIX.into()
+}
+
+impl Community {
+    /// Returns the community domain (e.g. `reddit.com/r/rust`). User concatenates it with the private key
+    /// to derive the community-specific key, so it must be unique among the communities.
+    pub fn domain(&self) -> String {
+        format!("{}/{}", self.domain_prefix.trim_end_matches('/'), self.name)
+    }
+
+    /// Checks that the community data is consistent.
+    pub fn validate(&self) -> Result<(), CommunityError> {
+        if self.name.is_empty() || self.erc20_token_name.is_empty() {
+            return Err(CommunityError::InvalidData(
+                "community name and token name must not be empty".into(),
+            ));
+        }
+
+        if let Some(icon_url) = &self.icon_url {
+            if !icon_url.starts_with("https://") && !icon_url.starts_with("http://") {
+                return Err(CommunityError::InvalidData(
+                    "icon URL must be an HTTP(S) URL".into(),
+                ));
+            }
+        }
+
+        let mut item_ids = HashSet::new();
+        for item in &self.items {
+            if !item_ids.insert(&item.id) {
+                return Err(CommunityError::InvalidData(format!(
+                    "item id {} is not unique",
+                    item.id
+                )));
+            }
+            if item.price == 0 {
+                return Err(CommunityError::InvalidData(format!(
+                    "price of the item {} must be positive",
+                    item.id
+                )));
+            }
+        }
+
+        let mut tier_names = HashSet::new();
+        for tier in &self.holding_tiers {
+            if !tier_names.insert(&tier.name) {
+                return Err(CommunityError::InvalidData(format!(
+                    "holding tier name {} is not unique",
+                    tier.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts the amount of whole community tokens into the base units of the token used by zkSync,
+    /// e.g. 1 token with 18 decimals is 10^18 base units.
+    pub fn token_base_units(&self, amount: u64) -> BigUint {
+        BigUint::from(amount) * num::pow(BigUint::from(10u32), usize::from(self.token_decimals))
+    }
+
+    /// Returns the catalog item with the given identifier.
+    pub fn item(&self, item_id: &str) -> Option<&CatalogItem> {
+        self.items.iter().find(|item| item.id == item_id)
+    }
+
+    /// Returns the description of the community as it should be known to the Community Oracle.
+    pub fn oracle_description(&self) -> CommunityDescription {
+        CommunityDescription {
+            name: self.name.clone(),
+            token_id: self.zksync_token_id,
+            token_symbol: self.erc20_token_name.clone(),
+        }
+    }
+}
+
+/// Filter for the communities listing.
+#[derive(Debug, Clone, Default)]
+pub struct CommunityFilter {
+    /// If set, only communities which name starts with the prefix are listed (case-insensitive).
+    pub name_prefix: Option<String>,
+    /// If set, only communities with the token of the given symbol (case-insensitive) or address are listed.
+    pub token: Option<String>,
+    pub include_archived: bool,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl CommunityFilter {
+    pub fn matches(&self, community: &Community) -> bool {
+        if community.archived && !self.include_archived {
+            return false;
+        }
+
+        if let Some(prefix) = &self.name_prefix {
+            if !community
+                .name
+                .to_lowercase()
+                .starts_with(&prefix.to_lowercase())
+            {
+                return false;
+            }
+        }
+
+        if let Some(token) = &self.token {
+            let address_matches = token
+                .trim_start_matches("0x")
+                .parse::<Address>()
+                .map(|address| address == community.erc20_token_address)
+                .unwrap_or(false);
+
+            if !address_matches && !community.erc20_token_name.eq_ignore_ascii_case(token) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Record of the catalog item bought by user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Purchase {
+    pub community_name: String,
+    pub item_id: String,
+    /// Amount of tokens burnt for the item.
+    pub price: u64,
+    /// Hash of the burn transaction. Every transaction can be redeemed only once.
+    pub tx_hash: String,
+    pub purchased_at: DateTime<Utc>,
+}
+
+/// Maximum length of the Reddit username.
+pub const MAX_USERNAME_LENGTH: usize = 20;
+
+/// Reddit user linked to the main wallet address.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    /// Reddit username. Usernames are case-insensitive, but stored as provided on registration.
+    pub username: String,
+    pub address: Address,
+    pub registered_at: DateTime<Utc>,
+}
+
+impl User {
+    /// Checks that the username satisfies the Reddit rules: 3 to 20 characters, which are
+    /// letters, digits, underscores or dashes.
+    pub fn is_valid_username(username: &str) -> bool {
+        (3..=MAX_USERNAME_LENGTH).contains(&username.len())
+            && username
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -13,6 +236,20 @@ pub struct Subscription {
     pub service_name: String,
     pub subscription_wallet: Address,
     pub pre_signed_txs: Vec<SubscriptionTx>,
+    /// Time until which the subscription is paid by the previous subscription wallets.
+    pub carried_over_until: Option<DateTime<Utc>>,
+    /// Subscription wallets used before the migrations, from the oldest to the newest.
+    pub previous_wallets: Vec<PreviousSubscriptionWallet>,
+}
+
+/// Subscription wallet replaced during the migration.
+#[derive(Debug, Clone)]
+pub struct PreviousSubscriptionWallet {
+    pub wallet: Address,
+    pub pre_signed_txs: Vec<SubscriptionTx>,
+    pub closed_at: DateTime<Utc>,
+    /// Time until which the subscription was paid at the moment of migration.
+    pub paid_until: Option<DateTime<Utc>>,
 }
 
 impl Subscription {
@@ -21,10 +258,170 @@ impl Subscription {
             service_name: service_name.into(),
             subscription_wallet,
             pre_signed_txs: Vec::new(),
+            carried_over_until: None,
+            previous_wallets: Vec::new(),
+        }
+    }
+
+    /// Returns the end of the time paid by the stored pre-signed txs, including the time carried over from
+    /// the previous subscription wallets. Here we assume that txs are executed at ~same time as they become valid.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.pre_signed_txs
+            .iter()
+            .map(SubscriptionTxExt::valid_from)
+            .max()
+            .map(|date| date + Duration::days(SUBSCRIPTION_PERIOD_DAYS))
+            .max(self.carried_over_until)
+    }
+
+    /// Returns `true` if the subscription is (or may become) paid at the given time. Unlike
+    /// `ZksyncApp::check_subscription`, this check doesn't access zkSync and never sends the txs.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at()
+            .map_or(false, |expires_at| expires_at > now)
+    }
+
+    /// Returns the amount of the periods covered by the pre-signed txs which are not sent yet,
+    /// i.e. which validity windows haven't started at the given time.
+    pub fn remaining_periods(&self, now: DateTime<Utc>) -> usize {
+        self.pre_signed_txs
+            .iter()
+            .filter(|tx| tx.valid_from() > now)
+            .count()
+    }
+
+    /// Replaces the subscription wallet along with its pre-signed transactions. Previous wallet is kept
+    /// in the history, and the time it paid for is carried over to the new one.
+    pub fn migrate(
+        &mut self,
+        new_wallet: Address,
+        paid_until: Option<DateTime<Utc>>,
+        txs: Vec<SubscriptionTx>,
+    ) {
+        let previous = PreviousSubscriptionWallet {
+            wallet: self.subscription_wallet,
+            pre_signed_txs: std::mem::replace(&mut self.pre_signed_txs, txs),
+            closed_at: Utc::now(),
+            paid_until,
+        };
+
+        self.previous_wallets.push(previous);
+        self.subscription_wallet = new_wallet;
+        self.carried_over_until = paid_until;
+    }
+
+    /// Adds the pre-signed txs, keyed by their validity windows. Re-submitted txs are ignored, and txs for
+    /// the periods that haven't started yet are replaced (e.g. re-signed with a new fee). Txs for the started
+    /// periods may have been already sent, so an attempt to replace them fails without modifying the subscription.
+    pub fn add_subscription_txs(
+        &mut self,
+        new_txs: Vec<SubscriptionTx>,
+    ) -> Result<(), SubscriptionError> {
+        let now = Utc::now();
+        for new_tx in &new_txs {
+            let stored = self
+                .pre_signed_txs
+                .iter()
+                .find(|stored| stored.has_same_window(new_tx));
+
+            if let Some(stored) = stored {
+                if !stored.is_same_as(new_tx) && stored.valid_from() <= now {
+                    return Err(SubscriptionError::TxAlreadySent(stored.valid_from()));
+                }
+            }
+        }
+
+        for new_tx in new_txs {
+            let stored = self
+                .pre_signed_txs
+                .iter_mut()
+                .find(|stored| stored.has_same_window(&new_tx));
+
+            match stored {
+                Some(stored) => *stored = new_tx,
+                None => self.pre_signed_txs.push(new_tx),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeliveryStatus {
+    /// Delivery is waiting for the next attempt.
+    Pending,
+    Delivered,
+    /// All the attempts have failed, delivery can only be replayed manually.
+    Failed,
+}
+
+/// Delivery of the subscription event to a webhook, stored in the outbox until it's sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    /// Identifier assigned by the database.
+    pub id: u64,
+    /// Key of the event, see `SubscriptionEvent::key`.
+    pub event_key: String,
+    pub event: SubscriptionEvent,
+    pub url: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Time after which the pending delivery can be attempted.
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    pub fn new(event: SubscriptionEvent, url: impl Into<String>) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0,
+            event_key: event.key(),
+            event,
+            url: url.into(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+        }
+    }
+
+    /// Returns `true` if the delivery must be attempted at the given time.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.status == DeliveryStatus::Pending && self.next_attempt_at <= now
+    }
+
+    pub fn record_success(&mut self) {
+        self.attempts += 1;
+        self.status = DeliveryStatus::Delivered;
+        self.last_error = None;
+    }
+
+    /// Schedules the next attempt with the exponential backoff, or marks the delivery as failed
+    /// once the attempts are exhausted.
+    pub fn record_failure(&mut self, error: String, max_attempts: u32, base_delay: Duration) {
+        self.attempts += 1;
+        self.last_error = Some(error);
+
+        if self.attempts >= max_attempts {
+            self.status = DeliveryStatus::Failed;
+        } else {
+            // Exponent is capped, so the delay doesn't overflow for the large amounts of attempts.
+            let exponent = (self.attempts - 1).min(16);
+            self.next_attempt_at = Utc::now() + base_delay * 2i32.pow(exponent);
         }
     }
 
-    pub fn add_subscription_txs(&mut self, mut new_txs: Vec<SubscriptionTx>) {
-        self.pre_signed_txs.append(&mut new_txs);
+    /// Resets the delivery, so it's attempted again as if it was just created.
+    pub fn replay(&mut self) {
+        self.status = DeliveryStatus::Pending;
+        self.attempts = 0;
+        self.next_attempt_at = Utc::now();
     }
 }
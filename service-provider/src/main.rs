@@ -1,9 +1,13 @@
 //! Reddit Service Provider -- an application to built subscriptions support atop of the zkSync network.
 //!
 //! This application has the following modules:
+//! - `admin`: authorization of the requests to the private API;
+//! - `auth`: authentication of the users of the public API;
 //! - `config`: configuration of the application (can be loaded either from JSON or environment variables);
 //! - `database`: bindings of the application data schema to the database back-ends;
-//! - `oracle`: module of the interaction with the Community Oracle application;
+//! - `notifications`: delivery of the subscription events to the configured sinks;
+//! - `oracle`: interaction with the Community Oracle, either running as a separate service or embedded in-process;
+//! - `resilience`: retries, timeouts and circuit breaking for the requests to the external services;
 //! - `zksync`: module of the interaction with the zkSync network;
 //! - `utils`: minor helper functions;
 //! - `requests`: incoming request types for the API server;
@@ -19,10 +23,14 @@ use actix_web::{App, HttpServer};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+mod admin;
+mod auth;
 mod config;
 mod database;
+mod notifications;
 mod oracle;
 mod requests;
+mod resilience;
 mod responses;
 mod service_provider;
 mod utils;
@@ -30,15 +38,31 @@ mod zksync;
 
 async fn run_server(db: MemoryDb, config: AppConfig) -> std::io::Result<()> {
     let service_provider = ServiceProvider::new(db, config.clone());
+    actix_rt::spawn(service_provider.clone().run_subscription_checks());
+    actix_rt::spawn(service_provider.clone().run_webhook_deliveries());
+    actix_rt::spawn(service_provider.clone().run_oracle_health_probes());
 
-    HttpServer::new(move || {
-        let provider = service_provider.clone();
+    let public_provider = service_provider.clone();
+    let public_server = HttpServer::new(move || {
+        let provider = public_provider.clone();
         let app = provider.into_web_scope();
         App::new().service(app)
     })
     .bind(config.app_bind_address)?
-    .run()
-    .await
+    .run();
+
+    // Private API is served separately, so the public listener can't be used to modify the application state.
+    let admin_server = HttpServer::new(move || {
+        let provider = service_provider.clone();
+        let app = provider.into_admin_scope();
+        App::new().service(app)
+    })
+    .bind(config.admin_bind_address)?
+    .run();
+
+    tokio::try_join!(public_server, admin_server)?;
+
+    Ok(())
 }
 
 #[derive(Debug, StructOpt)]
@@ -0,0 +1,292 @@
+//! Notifications about the subscription events for the users and the external services.
+//!
+//! Events are emitted by the Service Provider and delivered to every configured sink (see `EventSinkConfig`).
+//! Events written to the log are delivered right away, while webhook deliveries are stored in the outbox
+//! (see `DatabaseAccess::add_event`) and sent by a background routine, which retries the failed ones.
+//! Thus webhooks are delivered at least once, and receivers must deduplicate the events by their `id`.
+//!
+//! Every webhook request is signed with the secret of the webhook and contains the following headers:
+//!
+//! - `X-Webhook-Timestamp`: UNIX timestamp (in seconds) of the moment request was sent.
+//! - `X-Webhook-Signature`: hex-encoded HMAC-SHA256 of the `"{timestamp}\n{body}"` string.
+
+use crate::{
+    config::{EventSinkConfig, ResilienceConfig},
+    database::WebhookDelivery,
+    resilience::Dependency,
+    zksync::Address,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde_derive::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Event related to the user subscription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SubscriptionEvent {
+    /// User has subscribed to the community.
+    #[serde(rename_all = "camelCase")]
+    Created {
+        user: Address,
+        community_name: String,
+        subscription_wallet: Address,
+    },
+    /// Pre-signed tx paying for the next subscription period was sent. Txs are sent when the subscription
+    /// status is checked (see `ServiceProvider::check_subscription`), so the event may be emitted later
+    /// than the period starts.
+    #[serde(rename_all = "camelCase")]
+    Renewed {
+        user: Address,
+        community_name: String,
+        period_start: DateTime<Utc>,
+    },
+    /// Pre-signed tx paying for the next subscription period was rejected by zkSync.
+    #[serde(rename_all = "camelCase")]
+    PaymentFailed {
+        user: Address,
+        community_name: String,
+        period_start: DateTime<Utc>,
+        reason: String,
+    },
+    /// Subscription won't be renewed after `expires_at`: it was removed along with its community, it has lapsed,
+    /// or its pre-signed txs were dropped along with the replaced subscription wallet.
+    #[serde(rename_all = "camelCase")]
+    Cancelled {
+        user: Address,
+        community_name: String,
+        expires_at: Option<DateTime<Utc>>,
+    },
+    /// User has replaced the subscription wallet, see `ServiceProvider::migrate_subscription`.
+    #[serde(rename_all = "camelCase")]
+    WalletMigrated {
+        user: Address,
+        community_name: String,
+        previous_wallet: Address,
+        subscription_wallet: Address,
+    },
+    /// Subscription has lapsed: there are no pre-signed txs for the periods after `expired_at`.
+    #[serde(rename_all = "camelCase")]
+    Expired {
+        user: Address,
+        community_name: String,
+        expired_at: DateTime<Utc>,
+    },
+    /// Remaining pre-signed txs cover fewer periods than configured, so user has to sign more of them
+    /// for the subscription to not lapse.
+    #[serde(rename_all = "camelCase")]
+    RenewalNeeded {
+        user: Address,
+        community_name: String,
+        /// Amount of the periods covered by the pre-signed txs that are not sent yet.
+        remaining_periods: usize,
+        expires_at: DateTime<Utc>,
+    },
+}
+
+impl SubscriptionEvent {
+    /// Returns the key identifying the event. Events with the same key are considered duplicates
+    /// and emitted only once, e.g. the expiration is reported once per subscription period end.
+    pub fn key(&self) -> String {
+        let (event_type, user, community_name, moment) = match self {
+            Self::Created {
+                user,
+                community_name,
+                subscription_wallet,
+            } => (
+                "created",
+                user,
+                community_name,
+                hex::encode(subscription_wallet.as_ref()),
+            ),
+            Self::Renewed {
+                user,
+                community_name,
+                period_start,
+            } => ("renewed", user, community_name, period_start.to_rfc3339()),
+            Self::PaymentFailed {
+                user,
+                community_name,
+                period_start,
+                ..
+            } => (
+                "paymentFailed",
+                user,
+                community_name,
+                period_start.to_rfc3339(),
+            ),
+            Self::Cancelled {
+                user,
+                community_name,
+                expires_at,
+            } => (
+                "cancelled",
+                user,
+                community_name,
+                expires_at.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            ),
+            Self::WalletMigrated {
+                user,
+                community_name,
+                subscription_wallet,
+                ..
+            } => (
+                "walletMigrated",
+                user,
+                community_name,
+                hex::encode(subscription_wallet.as_ref()),
+            ),
+            Self::Expired {
+                user,
+                community_name,
+                expired_at,
+            } => ("expired", user, community_name, expired_at.to_rfc3339()),
+            Self::RenewalNeeded {
+                user,
+                community_name,
+                expires_at,
+                ..
+            } => (
+                "renewalNeeded",
+                user,
+                community_name,
+                expires_at.to_rfc3339(),
+            ),
+        };
+
+        format!(
+            "{}:0x{}:{}:{}",
+            event_type,
+            hex::encode(user.as_ref()),
+            community_name,
+            moment
+        )
+    }
+}
+
+/// Body of the webhook request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookPayload<'a> {
+    /// Key of the event, same for all the deliveries of the event.
+    id: &'a str,
+    created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a SubscriptionEvent,
+}
+
+/// Calculates the signature of the webhook request.
+pub fn sign_payload(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_varkey(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(format!("{}\n", timestamp).as_bytes());
+    mac.update(body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[derive(Debug)]
+struct Webhook {
+    url: String,
+    secret: String,
+    client: Client,
+    dependency: Dependency,
+}
+
+/// Writes the events to the log and creates the webhook deliveries for them.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    log_events: bool,
+    webhooks: Arc<Vec<Webhook>>,
+}
+
+impl Notifier {
+    pub fn new(sinks: &[EventSinkConfig], resilience: ResilienceConfig) -> Self {
+        let mut log_events = false;
+        let mut webhooks = Vec::new();
+
+        for sink in sinks {
+            match sink {
+                EventSinkConfig::Log => log_events = true,
+                EventSinkConfig::Webhook { url, secret } => {
+                    let dependency =
+                        Dependency::new(format!("webhook ({})", url), resilience.clone());
+
+                    webhooks.push(Webhook {
+                        url: url.clone(),
+                        secret: secret.clone(),
+                        client: dependency.http_client(),
+                        dependency,
+                    });
+                }
+            }
+        }
+
+        Self {
+            log_events,
+            webhooks: Arc::new(webhooks),
+        }
+    }
+
+    /// Writes the event to the log, if it's configured as a sink.
+    pub fn log(&self, event: &SubscriptionEvent) {
+        if self.log_events {
+            match serde_json::to_string(event) {
+                Ok(event) => log::info!("Subscription event: {}", event),
+                Err(error) => log::error!("Unable to encode the event {:?}: {}", event, error),
+            }
+        }
+    }
+
+    /// Creates a pending delivery of the event for every configured webhook.
+    pub fn deliveries(&self, event: &SubscriptionEvent) -> Vec<WebhookDelivery> {
+        self.webhooks
+            .iter()
+            .map(|webhook| WebhookDelivery::new(event.clone(), webhook.url.clone()))
+            .collect()
+    }
+
+    /// Performs a single attempt to send the delivery to its webhook. Failed deliveries are
+    /// retried by the outbox rather than by this method.
+    pub async fn send(&self, delivery: &WebhookDelivery) -> Result<()> {
+        let webhook = self
+            .webhooks
+            .iter()
+            .find(|webhook| webhook.url == delivery.url)
+            .ok_or_else(|| anyhow!("Webhook {} is not configured anymore", delivery.url))?;
+
+        let body = &serde_json::to_vec(&WebhookPayload {
+            id: &delivery.event_key,
+            created_at: delivery.created_at,
+            event: &delivery.event,
+        })?;
+
+        webhook
+            .dependency
+            .call(false, || async move {
+                let timestamp = Utc::now().timestamp() as u64;
+                let signature = sign_payload(&webhook.secret, timestamp, body);
+
+                webhook
+                    .client
+                    .post(&webhook.url)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(SIGNATURE_HEADER, signature)
+                    .body(body.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+
+                Ok(())
+            })
+            .await
+    }
+}
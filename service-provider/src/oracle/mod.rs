@@ -0,0 +1,372 @@
+//! This module provides a `CommunityOracle` type: an entity capable of
+//! the interaction with the Community Oracle.
+//!
+//! Oracle can either run as a separate service (see the `remote` module), or be hosted by the
+//! Service Provider in-process (embedded mode). In both cases, the results are represented with
+//! the types from the `community_oracle::responses` module, so the Service Provider
+//! can inspect them and build its own responses.
+
+use crate::{
+    config::AppConfig,
+    resilience::{is_transient_status, CircuitOpenError, DependencyError, DependencyStatus},
+    responses::{
+        BatchMintingSignatureResponse, ClaimStatusResponse, CustodialMintResponse, ErrorResponse,
+        GenesisWalletAddressResponse, MintingSignatureResponse,
+        MintingSignatureVerificationResponse, RegisterSigningKeyResponse,
+        RelatedCommunitiesResponse,
+    },
+};
+use community_oracle::{
+    community_oracle::CommunityOracle as EmbeddedOracle,
+    eligibility::EligibilityRules,
+    error::RequestError,
+    requests::{
+        AllowlistModeRequest, BatchMintingSignatureRequest, ClaimStatusRequest,
+        CommunityUserRequest, CustodialMintRequest,
+        DeclareCommunityRequest as OracleDeclareCommunityRequest, EligibilityRulesRequest,
+        GrantedTokensRequest, MinKarmaRequest, MintingSignatureRequest,
+        MintingSignatureVerificationRequest, RegisterSigningKeyRequest, RelatedCommunitiesRequest,
+        RemoveCommunityRequest, UserKarmaRequest,
+    },
+    responses::{CommunitiesResponse, DeclareCommunityResponse, GrantedTokensResponse},
+};
+use reqwest::StatusCode;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub use self::remote::RemoteOracle;
+
+mod remote;
+
+/// Name under which the embedded oracle is reported as an oracle instance.
+pub const EMBEDDED_INSTANCE: &str = "embedded";
+
+#[derive(Error, Debug)]
+pub enum OracleError {
+    /// Oracle considers the request incorrect (e.g. unknown community or ineligible user).
+    #[error("Community Oracle rejected the request: {}", .0.error_description)]
+    Rejected(ErrorResponse),
+    #[error("Community Oracle rejected the request authentication: {}", .0.error_description)]
+    Unauthorized(ErrorResponse),
+    #[error("Community Oracle failed to process the request: {}", .0.error_description)]
+    Internal(ErrorResponse),
+    #[error("Unexpected response status from the Community Oracle: {0}")]
+    UnexpectedStatus(StatusCode),
+    #[error("Unable to decode response from the Community Oracle: {0}")]
+    InvalidResponse(String),
+    #[error("Unable to prepare request to the Community Oracle: {0}")]
+    InvalidRequest(String),
+    #[error("Unable to send request to the Community Oracle: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("{0}")]
+    CircuitOpen(#[from] CircuitOpenError),
+}
+
+impl DependencyError for OracleError {
+    fn is_transient(&self) -> bool {
+        match self {
+            OracleError::Network(_) | OracleError::Internal(_) => true,
+            OracleError::UnexpectedStatus(status) => is_transient_status(*status),
+            _ => false,
+        }
+    }
+}
+
+impl From<RequestError> for OracleError {
+    fn from(error: RequestError) -> Self {
+        match error {
+            RequestError::Rejected(error) => OracleError::Rejected(ErrorResponse {
+                error_description: error.error_description,
+            }),
+            RequestError::Internal(error) => OracleError::Internal(ErrorResponse {
+                error_description: error.error_description,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CommunityOracle {
+    /// Oracle is a separate service accessed through its HTTP API.
+    Remote(RemoteOracle),
+    /// Oracle logic is executed within the Service Provider process.
+    Embedded(Arc<EmbeddedOracle>),
+}
+
+fn remote_eligibility_error() -> OracleError {
+    OracleError::Rejected(ErrorResponse::error(
+        "Eligibility rules of the remote Community Oracle are managed through its admin API",
+    ))
+}
+
+impl CommunityOracle {
+    /// Creates the oracle in the mode chosen by the configuration.
+    pub fn new(config: &AppConfig) -> Self {
+        match &config.embedded_oracle {
+            Some(oracle_config) => {
+                log::info!("Community Oracle is running in the embedded mode");
+                Self::Embedded(Arc::new(EmbeddedOracle::new(oracle_config.clone())))
+            }
+            None => Self::Remote(RemoteOracle::new(
+                config.community_oracle_addresses.clone(),
+                config
+                    .community_oracle_api_secret
+                    .clone()
+                    .expect("community_oracle_api_secret is required for the remote oracle"),
+                config.resilience.clone(),
+            )),
+        }
+    }
+
+    /// Returns the status of every remote oracle instance. Embedded oracle has no instances to report.
+    pub fn status(&self) -> Vec<DependencyStatus> {
+        match self {
+            Self::Remote(oracle) => oracle.status(),
+            Self::Embedded(_) => Vec::new(),
+        }
+    }
+
+    /// Periodically probes the remote oracle instances, see `RemoteOracle::run_health_probes`.
+    /// Embedded oracle needs no probes. Must be spawned once on the application start.
+    pub async fn run_health_probes(self) {
+        if let Self::Remote(oracle) = self {
+            oracle.run_health_probes().await;
+        }
+    }
+
+    /// Returns `true` if requests to the oracle can be processed at the moment.
+    pub fn is_available(&self) -> bool {
+        match self {
+            Self::Remote(oracle) => oracle.is_available(),
+            Self::Embedded(_) => true,
+        }
+    }
+
+    pub async fn tokens_for_user(
+        &self,
+        request: GrantedTokensRequest,
+    ) -> Result<GrantedTokensResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.tokens_for_user(request).await,
+            Self::Embedded(oracle) => Ok(oracle.tokens_for_user(request).await?),
+        }
+    }
+
+    pub async fn sign_minting_tx(
+        &self,
+        request: MintingSignatureRequest,
+    ) -> Result<MintingSignatureResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.sign_minting_tx(request).await,
+            Self::Embedded(oracle) => Ok(oracle.sign_minting_tx(request).await?),
+        }
+    }
+
+    pub async fn sign_minting_txs_batch(
+        &self,
+        request: BatchMintingSignatureRequest,
+    ) -> Result<BatchMintingSignatureResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.sign_minting_txs_batch(request).await,
+            Self::Embedded(oracle) => Ok(oracle.sign_minting_txs_batch(request).await?),
+        }
+    }
+
+    pub async fn verify_minting_signature(
+        &self,
+        request: MintingSignatureVerificationRequest,
+    ) -> Result<MintingSignatureVerificationResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.verify_minting_signature(request).await,
+            Self::Embedded(oracle) => Ok(oracle.verify_minting_signature(request).await?),
+        }
+    }
+
+    pub async fn claim_status(
+        &self,
+        request: ClaimStatusRequest,
+    ) -> Result<ClaimStatusResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.claim_status(request).await,
+            Self::Embedded(oracle) => Ok(oracle.claim_status(request).await?),
+        }
+    }
+
+    pub async fn register_signing_key(
+        &self,
+        request: RegisterSigningKeyRequest,
+    ) -> Result<RegisterSigningKeyResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.register_signing_key(request).await,
+            Self::Embedded(oracle) => Ok(oracle.register_signing_key(request).await?),
+        }
+    }
+
+    pub async fn custodial_mint(
+        &self,
+        request: CustodialMintRequest,
+    ) -> Result<CustodialMintResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.custodial_mint(request).await,
+            Self::Embedded(oracle) => Ok(oracle.custodial_mint(request).await?),
+        }
+    }
+
+    pub async fn related_communities(
+        &self,
+        request: RelatedCommunitiesRequest,
+    ) -> Result<RelatedCommunitiesResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.related_communities(request).await,
+            Self::Embedded(oracle) => Ok(oracle.related_communities(request).await?),
+        }
+    }
+
+    pub async fn genesis_wallet_address(
+        &self,
+    ) -> Result<GenesisWalletAddressResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.genesis_wallet_address().await,
+            Self::Embedded(oracle) => Ok(oracle.genesis_wallet_address().await?),
+        }
+    }
+
+    /// Declares the community in every oracle instance, returning the result of each instance.
+    pub async fn declare_community(
+        &self,
+        request: OracleDeclareCommunityRequest,
+    ) -> Vec<(String, Result<DeclareCommunityResponse, OracleError>)> {
+        match self {
+            Self::Remote(oracle) => oracle.declare_community(request).await,
+            Self::Embedded(oracle) => {
+                let result = oracle.declare_community(request).await;
+                vec![(EMBEDDED_INSTANCE.to_string(), result.map_err(From::from))]
+            }
+        }
+    }
+
+    /// Removes the community from every oracle instance, returning the result of each instance.
+    pub async fn remove_community(
+        &self,
+        request: RemoveCommunityRequest,
+    ) -> Vec<(String, Result<(), OracleError>)> {
+        match self {
+            Self::Remote(oracle) => oracle.remove_community(request).await,
+            Self::Embedded(oracle) => {
+                let result = oracle.remove_community(request).await;
+                vec![(EMBEDDED_INSTANCE.to_string(), result.map_err(From::from))]
+            }
+        }
+    }
+
+    pub async fn communities(&self) -> Result<CommunitiesResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.communities().await,
+            Self::Embedded(oracle) => Ok(oracle.communities().await?),
+        }
+    }
+
+    // Eligibility rules are only managed through the Service Provider in the embedded mode,
+    // since remote oracle instances serve their own admin API for that.
+    pub async fn eligibility_rules(
+        &self,
+        request: EligibilityRulesRequest,
+    ) -> Result<EligibilityRules, OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.eligibility_rules(request).await?),
+        }
+    }
+
+    pub async fn ban_user(&self, request: CommunityUserRequest) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.ban_user(request).await?),
+        }
+    }
+
+    pub async fn unban_user(&self, request: CommunityUserRequest) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.unban_user(request).await?),
+        }
+    }
+
+    pub async fn set_allowlist_mode(
+        &self,
+        request: AllowlistModeRequest,
+    ) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.set_allowlist_mode(request).await?),
+        }
+    }
+
+    pub async fn allow_user(&self, request: CommunityUserRequest) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.allow_user(request).await?),
+        }
+    }
+
+    pub async fn disallow_user(&self, request: CommunityUserRequest) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.disallow_user(request).await?),
+        }
+    }
+
+    pub async fn set_min_karma(&self, request: MinKarmaRequest) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.set_min_karma(request).await?),
+        }
+    }
+
+    pub async fn set_user_karma(&self, request: UserKarmaRequest) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(_) => Err(remote_eligibility_error()),
+            Self::Embedded(oracle) => Ok(oracle.set_user_karma(request).await?),
+        }
+    }
+
+    /// Returns the names of the oracle instances, each of which keeps its own community registry.
+    pub fn instances(&self) -> Vec<String> {
+        match self {
+            Self::Remote(oracle) => oracle.instances(),
+            Self::Embedded(_) => vec![EMBEDDED_INSTANCE.to_string()],
+        }
+    }
+
+    pub async fn instance_communities(
+        &self,
+        instance: &str,
+    ) -> Result<CommunitiesResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.instance_communities(instance).await,
+            Self::Embedded(oracle) => Ok(oracle.communities().await?),
+        }
+    }
+
+    pub async fn declare_instance_community(
+        &self,
+        instance: &str,
+        request: OracleDeclareCommunityRequest,
+    ) -> Result<DeclareCommunityResponse, OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.declare_instance_community(instance, request).await,
+            Self::Embedded(oracle) => Ok(oracle.declare_community(request).await?),
+        }
+    }
+
+    pub async fn remove_instance_community(
+        &self,
+        instance: &str,
+        request: RemoveCommunityRequest,
+    ) -> Result<(), OracleError> {
+        match self {
+            Self::Remote(oracle) => oracle.remove_instance_community(instance, request).await,
+            Self::Embedded(oracle) => Ok(oracle.remove_community(request).await?),
+        }
+    }
+}
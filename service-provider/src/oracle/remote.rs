@@ -0,0 +1,513 @@
+//! Client of the Community Oracle application running as a separate service.
+//! Basically, all it does is performs an API requests and decodes the responses into
+//! the types from the `community_oracle::responses` module.
+//!
+//! Every request is signed with the secret shared with the oracle, since oracle
+//! rejects unauthenticated requests.
+//!
+//! Client can work with several oracle instances: requests are distributed among them in the
+//! round-robin manner, and instances which are considered down by their circuit breakers are skipped.
+//! If the request to one instance fails, it's sent to the next one (see `RemoteOracle::request`).
+//!
+//! Every instance is also probed periodically (see `RemoteOracle::run_health_probes`), so instances which
+//! are down are detected before user requests reach them, and recovered instances are brought back
+//! without waiting for user requests to probe them.
+//!
+//! Requests modifying the community registry are the exception: every instance keeps its own registry,
+//! so they are sent to all the instances (see `RemoteOracle::broadcast`).
+//!
+//! Requests depending on the claims of user (minting signatures, claim status and custodial mint) are
+//! the exception as well: every instance keeps its own claims history and enforces the once-per-round limit
+//! on its own, so requests of the same user are always sent to the same instance (see `RemoteOracle::request_pinned`).
+//! They are not passed to another instance if it's unavailable.
+
+use super::OracleError;
+use crate::{
+    config::ResilienceConfig,
+    resilience::{CircuitState, Dependency, DependencyError, DependencyStatus},
+    responses::{
+        BatchMintingSignatureResponse, ClaimStatusResponse, CustodialMintResponse, ErrorResponse,
+        GenesisWalletAddressResponse, MintingSignatureResponse,
+        MintingSignatureVerificationResponse, RegisterSigningKeyResponse,
+        RelatedCommunitiesResponse,
+    },
+    zksync::Address,
+};
+use chrono::Utc;
+use community_oracle::{
+    auth,
+    requests::{
+        BatchMintingSignatureRequest, ClaimStatusRequest, CustodialMintRequest,
+        DeclareCommunityRequest as OracleDeclareCommunityRequest, GrantedTokensRequest,
+        MintingSignatureRequest, MintingSignatureVerificationRequest, RegisterSigningKeyRequest,
+        RelatedCommunitiesRequest, RemoveCommunityRequest,
+    },
+    responses::{CommunitiesResponse, DeclareCommunityResponse, GrantedTokensResponse},
+};
+use reqwest::{header::CONTENT_TYPE, Client, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+impl OracleError {
+    /// Returns `true` if the request was certainly not received by the oracle instance,
+    /// so it can be sent to another instance even if it's not safe to repeat.
+    fn is_undelivered(&self) -> bool {
+        match self {
+            OracleError::CircuitOpen(_) => true,
+            OracleError::Network(error) => error.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+const TOKENS_FOR_USER_PATH: &str = "/api/v0.1/granted_tokens";
+const SIGN_MINTING_TX_PATH: &str = "/api/v0.1/get_minting_signature";
+const SIGN_MINTING_TXS_BATCH_PATH: &str = "/api/v0.1/get_minting_signatures_batch";
+const VERIFY_MINTING_SIGNATURE_PATH: &str = "/api/v0.1/verify_minting_signature";
+const CLAIM_STATUS_PATH: &str = "/api/v0.1/claim_status";
+const REGISTER_SIGNING_KEY_PATH: &str = "/api/v0.1/custodial/register_signing_key";
+const CUSTODIAL_MINT_PATH: &str = "/api/v0.1/custodial/mint";
+const RELATED_COMMUNITIES_PATH: &str = "/api/v0.1/related_communities";
+const GENESIS_WALLET_ADDRESS_PATH: &str = "/api/v0.1/genesis_wallet_address";
+const DECLARE_COMMUNITY_PATH: &str = "/api/v0.1/registry/declare_community";
+const REMOVE_COMMUNITY_PATH: &str = "/api/v0.1/registry/remove_community";
+const COMMUNITIES_PATH: &str = "/api/v0.1/registry/communities";
+
+/// Single instance of the Community Oracle.
+#[derive(Debug)]
+struct OracleInstance {
+    address: String,
+    client: Client,
+    dependency: Dependency,
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteOracle {
+    instances: Arc<Vec<OracleInstance>>,
+    next_instance: Arc<AtomicUsize>,
+    api_secret: String,
+    health_check_interval: Duration,
+}
+
+impl RemoteOracle {
+    pub fn new(
+        oracle_addresses: Vec<String>,
+        api_secret: impl Into<String>,
+        resilience: ResilienceConfig,
+    ) -> Self {
+        assert!(
+            !oracle_addresses.is_empty(),
+            "At least one Community Oracle address must be provided in the remote oracle mode"
+        );
+
+        if oracle_addresses.len() > 1 {
+            log::warn!(
+                "Eligibility rules of the Community Oracle are not shared between the instances, \
+                 so they are only applied by the instance they are set to"
+            );
+        }
+
+        let health_check_interval = Duration::from_millis(resilience.health_check_interval_ms);
+        let instances = oracle_addresses
+            .into_iter()
+            .map(|address| {
+                let address = address.trim_end_matches('/').to_string();
+                let dependency = Dependency::new(
+                    format!("community_oracle ({})", address),
+                    resilience.clone(),
+                );
+
+                OracleInstance {
+                    client: dependency.http_client(),
+                    address,
+                    dependency,
+                }
+            })
+            .collect();
+
+        Self {
+            instances: Arc::new(instances),
+            next_instance: Arc::new(AtomicUsize::new(0)),
+            api_secret: api_secret.into(),
+            health_check_interval,
+        }
+    }
+
+    /// Periodically sends a probe request to every oracle instance through its circuit breaker.
+    /// Once the circuit of an instance is half-open, the probe serves as its trial request.
+    /// Must be spawned once on the application start.
+    pub async fn run_health_probes(self) {
+        let mut timer = tokio::time::interval(self.health_check_interval);
+
+        loop {
+            timer.tick().await;
+
+            for instance in self.instances.iter() {
+                let result: Result<GenesisWalletAddressResponse, _> = self
+                    .request_instance(instance, GENESIS_WALLET_ADDRESS_PATH, &(), false)
+                    .await;
+
+                match result {
+                    Ok(_) | Err(OracleError::CircuitOpen(_)) => {}
+                    Err(error) => log::warn!(
+                        "Health probe of the community oracle {} failed: {}",
+                        instance.address,
+                        error
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Returns the status of every oracle instance.
+    pub fn status(&self) -> Vec<DependencyStatus> {
+        self.instances
+            .iter()
+            .map(|instance| instance.dependency.status())
+            .collect()
+    }
+
+    /// Returns `true` if at least one oracle instance is considered available.
+    pub fn is_available(&self) -> bool {
+        self.instances
+            .iter()
+            .any(|instance| instance.dependency.status().state != CircuitState::Open)
+    }
+
+    pub async fn tokens_for_user(
+        &self,
+        request: GrantedTokensRequest,
+    ) -> Result<GrantedTokensResponse, OracleError> {
+        self.request(TOKENS_FOR_USER_PATH, &request, true).await
+    }
+
+    pub async fn sign_minting_tx(
+        &self,
+        request: MintingSignatureRequest,
+    ) -> Result<MintingSignatureResponse, OracleError> {
+        self.request_pinned(request.user, SIGN_MINTING_TX_PATH, &request, true)
+            .await
+    }
+
+    pub async fn sign_minting_txs_batch(
+        &self,
+        request: BatchMintingSignatureRequest,
+    ) -> Result<BatchMintingSignatureResponse, OracleError> {
+        // All the items of the batch belong to the same user.
+        match request.requests.first().map(|item| item.user) {
+            Some(user) => {
+                self.request_pinned(user, SIGN_MINTING_TXS_BATCH_PATH, &request, true)
+                    .await
+            }
+            None => {
+                self.request(SIGN_MINTING_TXS_BATCH_PATH, &request, true)
+                    .await
+            }
+        }
+    }
+
+    pub async fn verify_minting_signature(
+        &self,
+        request: MintingSignatureVerificationRequest,
+    ) -> Result<MintingSignatureVerificationResponse, OracleError> {
+        self.request(VERIFY_MINTING_SIGNATURE_PATH, &request, true)
+            .await
+    }
+
+    pub async fn claim_status(
+        &self,
+        request: ClaimStatusRequest,
+    ) -> Result<ClaimStatusResponse, OracleError> {
+        self.request_pinned(request.user, CLAIM_STATUS_PATH, &request, true)
+            .await
+    }
+
+    pub async fn register_signing_key(
+        &self,
+        request: RegisterSigningKeyRequest,
+    ) -> Result<RegisterSigningKeyResponse, OracleError> {
+        self.request_pinned(request.user, REGISTER_SIGNING_KEY_PATH, &request, true)
+            .await
+    }
+
+    pub async fn custodial_mint(
+        &self,
+        request: CustodialMintRequest,
+    ) -> Result<CustodialMintResponse, OracleError> {
+        self.request_pinned(request.user, CUSTODIAL_MINT_PATH, &request, false)
+            .await
+    }
+
+    pub async fn related_communities(
+        &self,
+        request: RelatedCommunitiesRequest,
+    ) -> Result<RelatedCommunitiesResponse, OracleError> {
+        self.request(RELATED_COMMUNITIES_PATH, &request, true).await
+    }
+
+    pub async fn genesis_wallet_address(
+        &self,
+    ) -> Result<GenesisWalletAddressResponse, OracleError> {
+        self.request(GENESIS_WALLET_ADDRESS_PATH, &(), true).await
+    }
+
+    /// Declares the community in every oracle instance. Returns the result of every instance
+    /// along with its address, since instances may succeed or fail independently.
+    pub async fn declare_community(
+        &self,
+        request: OracleDeclareCommunityRequest,
+    ) -> Vec<(String, Result<DeclareCommunityResponse, OracleError>)> {
+        self.broadcast(DECLARE_COMMUNITY_PATH, &request).await
+    }
+
+    /// Removes the community from every oracle instance. Returns the result of every instance
+    /// along with its address, since instances may succeed or fail independently.
+    pub async fn remove_community(
+        &self,
+        request: RemoveCommunityRequest,
+    ) -> Vec<(String, Result<(), OracleError>)> {
+        self.broadcast(REMOVE_COMMUNITY_PATH, &request).await
+    }
+
+    pub async fn communities(&self) -> Result<CommunitiesResponse, OracleError> {
+        self.request(COMMUNITIES_PATH, &(), true).await
+    }
+
+    /// Returns the addresses of the oracle instances.
+    pub fn instances(&self) -> Vec<String> {
+        self.instances
+            .iter()
+            .map(|instance| instance.address.clone())
+            .collect()
+    }
+
+    /// Returns the communities known to the given oracle instance.
+    pub async fn instance_communities(
+        &self,
+        address: &str,
+    ) -> Result<CommunitiesResponse, OracleError> {
+        let instance = self.instance(address)?;
+        self.request_instance(instance, COMMUNITIES_PATH, &(), true)
+            .await
+    }
+
+    /// Declares the community in the given oracle instance only.
+    pub async fn declare_instance_community(
+        &self,
+        address: &str,
+        request: OracleDeclareCommunityRequest,
+    ) -> Result<DeclareCommunityResponse, OracleError> {
+        let instance = self.instance(address)?;
+        self.request_instance(instance, DECLARE_COMMUNITY_PATH, &request, false)
+            .await
+    }
+
+    /// Removes the community from the given oracle instance only.
+    pub async fn remove_instance_community(
+        &self,
+        address: &str,
+        request: RemoveCommunityRequest,
+    ) -> Result<(), OracleError> {
+        let instance = self.instance(address)?;
+        self.request_instance(instance, REMOVE_COMMUNITY_PATH, &request, false)
+            .await
+    }
+
+    fn instance(&self, address: &str) -> Result<&OracleInstance, OracleError> {
+        self.instances
+            .iter()
+            .find(|instance| instance.address == address)
+            .ok_or_else(|| {
+                OracleError::InvalidRequest(format!("Unknown oracle instance: {}", address))
+            })
+    }
+
+    /// Sends the request which is not safe to repeat to every oracle instance, and returns
+    /// the results along with the instance addresses. Failure of one instance doesn't prevent
+    /// the request from being sent to the rest of them.
+    async fn broadcast<Req, Resp>(
+        &self,
+        path: &str,
+        request: &Req,
+    ) -> Vec<(String, Result<Resp, OracleError>)>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let mut responses = Vec::with_capacity(self.instances.len());
+        for instance in self.instances.iter() {
+            let response = self.request_instance(instance, path, request, false).await;
+            if let Err(error) = &response {
+                log::warn!(
+                    "Community oracle {} failed to process {}: {}",
+                    instance.address,
+                    path,
+                    error
+                );
+            }
+            responses.push((instance.address.clone(), response));
+        }
+
+        responses
+    }
+
+    /// Sends the request to the given oracle instance through its circuit breaker.
+    async fn request_instance<Req, Resp>(
+        &self,
+        instance: &OracleInstance,
+        path: &str,
+        request: &Req,
+        idempotent: bool,
+    ) -> Result<Resp, OracleError>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        instance
+            .dependency
+            .call(idempotent, || async move {
+                // Request is signed on every attempt, since oracle doesn't accept reused nonces.
+                let response = self.post(instance, path, request).await?;
+
+                Self::decode_response(response).await
+            })
+            .await
+    }
+
+    /// Sends the request to the oracle and decodes the response.
+    /// Requests which are safe to repeat must be marked as `idempotent` to be retried on failures.
+    ///
+    /// Request is sent to the next oracle instance in the round-robin order. If the instance
+    /// is unavailable, request is sent to the following one, until all the instances are tried.
+    /// Requests that are not safe to repeat are passed to another instance only if the previous
+    /// one certainly didn't receive it.
+    async fn request<Req, Resp>(
+        &self,
+        path: &str,
+        request: &Req,
+        idempotent: bool,
+    ) -> Result<Resp, OracleError>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let instances_count = self.instances.len();
+        let first_instance = self.next_instance.fetch_add(1, Ordering::Relaxed);
+        let mut last_error = None;
+
+        for offset in 0..instances_count {
+            let instance = &self.instances[(first_instance + offset) % instances_count];
+
+            let result = self
+                .request_instance(instance, path, request, idempotent)
+                .await;
+
+            match result {
+                Err(error) if error.is_undelivered() || (idempotent && error.is_transient()) => {
+                    log::warn!(
+                        "Community oracle {} is unavailable, trying the next instance: {}",
+                        instance.address,
+                        error
+                    );
+                    last_error = Some(error);
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_error.expect("There is at least one oracle instance"))
+    }
+
+    /// Sends the request to the oracle instance assigned to the user. Instance is chosen by the user
+    /// address, so requests of the same user always reach the same instance.
+    async fn request_pinned<Req, Resp>(
+        &self,
+        user: Address,
+        path: &str,
+        request: &Req,
+        idempotent: bool,
+    ) -> Result<Resp, OracleError>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let mut user_bytes = [0u8; 8];
+        user_bytes.copy_from_slice(&user.as_ref()[12..]);
+        let index = u64::from_be_bytes(user_bytes) % self.instances.len() as u64;
+        let instance = &self.instances[index as usize];
+
+        self.request_instance(instance, path, request, idempotent)
+            .await
+    }
+
+    /// Sends a POST request to the oracle instance, signed with the shared secret.
+    /// See the `community_oracle::auth` module for details.
+    async fn post(
+        &self,
+        instance: &OracleInstance,
+        path: &str,
+        request: &impl Serialize,
+    ) -> Result<reqwest::Response, OracleError> {
+        let body = serde_json::to_vec(request)
+            .map_err(|error| OracleError::InvalidRequest(error.to_string()))?;
+        let endpoint = format!("{}{}", instance.address, path);
+        let timestamp = Utc::now().timestamp() as u64;
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let signature = auth::sign_request(&self.api_secret, timestamp, &nonce, path, &body);
+
+        let response = instance
+            .client
+            .post(&endpoint)
+            .header(CONTENT_TYPE, "application/json")
+            .header(auth::TIMESTAMP_HEADER, timestamp.to_string())
+            .header(auth::NONCE_HEADER, nonce)
+            .header(auth::SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Decodes the oracle response: successful responses are decoded into the expected type,
+    /// and error responses are mapped onto the `OracleError` variants.
+    async fn decode_response<T: DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T, OracleError> {
+        let status = response.status();
+
+        if status == StatusCode::OK {
+            return response.json().await.map_err(|error| {
+                log::error!("Community oracle returned incorrect JSON: {}", error);
+                OracleError::InvalidResponse(error.to_string())
+            });
+        }
+
+        let error_builder: fn(ErrorResponse) -> OracleError = match status {
+            StatusCode::BAD_REQUEST => OracleError::Rejected,
+            StatusCode::UNAUTHORIZED => OracleError::Unauthorized,
+            StatusCode::INTERNAL_SERVER_ERROR => OracleError::Internal,
+            _ => {
+                log::error!(
+                    "Community oracle returned unexpected response: {:?}",
+                    response
+                );
+                return Err(OracleError::UnexpectedStatus(status));
+            }
+        };
+
+        match response.json::<ErrorResponse>().await {
+            Ok(error) => Err(error_builder(error)),
+            Err(error) => {
+                log::error!("Community oracle returned incorrect JSON: {}", error);
+                Err(OracleError::InvalidResponse(error.to_string()))
+            }
+        }
+    }
+}
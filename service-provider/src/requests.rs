@@ -1,17 +1,161 @@
 //! Type definitions for the API requests of the Service Provider.
 //! Note that some of types are re-exported from the `community-oracle` crate.
 //! See the `community_oracle::requests` module for their definitions.
+//!
+//! Requests proxied to the Community Oracle which refer to a user mirror the oracle types, but accept
+//! the user in the `UserRef` form. They are converted into the oracle requests once the user is resolved.
 
 use crate::{
-    database::Community,
-    zksync::{Address, SubscriptionTx},
+    database::{Community, DeliveryStatus},
+    zksync::{Address, PackedEthSignature, SubscriptionTx},
 };
 use serde_derive::{Deserialize, Serialize};
 
+use community_oracle::{requests as oracle, zksync::MintingTransaction};
+
 pub use community_oracle::requests::{
-    GrantedTokensRequest, MintingSignatureRequest, RelatedCommunitiesRequest,
+    AllowlistModeRequest, EligibilityRulesRequest, MinKarmaRequest,
+    MintingSignatureVerificationRequest,
 };
 
+/// Reference to the user: either the address of the main wallet, or the registered Reddit username.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UserRef {
+    Address(Address),
+    Username(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantedTokensRequest {
+    pub user: UserRef,
+    pub community_name: String,
+}
+
+impl GrantedTokensRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::GrantedTokensRequest {
+        oracle::GrantedTokensRequest {
+            user,
+            community_name: self.community_name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintingSignatureRequest {
+    pub user: UserRef,
+    pub community_name: String,
+    pub minting_tx: MintingTransaction,
+}
+
+impl MintingSignatureRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::MintingSignatureRequest {
+        oracle::MintingSignatureRequest {
+            user,
+            community_name: self.community_name,
+            minting_tx: self.minting_tx,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMintingSignatureRequest {
+    pub requests: Vec<MintingSignatureRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimStatusRequest {
+    pub user: UserRef,
+    pub community_name: String,
+}
+
+impl ClaimStatusRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::ClaimStatusRequest {
+        oracle::ClaimStatusRequest {
+            user,
+            community_name: self.community_name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterSigningKeyRequest {
+    pub user: UserRef,
+}
+
+impl RegisterSigningKeyRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::RegisterSigningKeyRequest {
+        oracle::RegisterSigningKeyRequest { user }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustodialMintRequest {
+    pub user: UserRef,
+    pub community_name: String,
+}
+
+impl CustodialMintRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::CustodialMintRequest {
+        oracle::CustodialMintRequest {
+            user,
+            community_name: self.community_name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedCommunitiesRequest {
+    pub user: UserRef,
+}
+
+impl RelatedCommunitiesRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::RelatedCommunitiesRequest {
+        oracle::RelatedCommunitiesRequest { user }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityUserRequest {
+    pub community_name: String,
+    pub user: UserRef,
+}
+
+impl CommunityUserRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::CommunityUserRequest {
+        oracle::CommunityUserRequest {
+            community_name: self.community_name,
+            user,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserKarmaRequest {
+    pub community_name: String,
+    pub user: UserRef,
+    pub karma: u64,
+}
+
+impl UserKarmaRequest {
+    pub fn into_oracle_request(self, user: Address) -> oracle::UserKarmaRequest {
+        oracle::UserKarmaRequest {
+            community_name: self.community_name,
+            user,
+            karma: self.karma,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeclareCommunityRequest {
@@ -19,18 +163,148 @@ pub struct DeclareCommunityRequest {
     pub community: Community,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCommunityRequest {
+    /// New community data. `version` must be equal to the version of the community the update is based on.
+    #[serde(flatten)]
+    pub community: Community,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityRequest {
+    pub community_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileCommunitiesRequest {
+    /// If `true`, Community Oracle registry is updated to match the Service Provider one.
+    /// Otherwise, the differences are only reported.
+    #[serde(default)]
+    pub fix: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityListRequest {
+    /// List only communities which name starts with the prefix (case-insensitive).
+    pub name_prefix: Option<String>,
+    /// List only communities with the token of the given symbol or address.
+    pub token: Option<String>,
+    /// Whether to list the archived communities.
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Amount of communities to skip.
+    #[serde(default)]
+    pub offset: usize,
+    /// Maximum amount of communities to return. Default is `DEFAULT_PAGE_SIZE`, limited by `MAX_PAGE_SIZE`.
+    pub limit: Option<usize>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubscriptionCheckRequest {
-    pub user: Address,
+    pub user: UserRef,
     pub community_name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveriesRequest {
+    /// If set, only the deliveries with this status are returned.
+    #[serde(default)]
+    pub status: Option<DeliveryStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayWebhookDeliveriesRequest {
+    /// Identifiers of the deliveries to be attempted again.
+    pub ids: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendSubscriptionRequest {
+    pub user: UserRef,
+    pub community_name: String,
+    /// Pre-signed txs for the next subscription periods.
+    pub txs: Vec<SubscriptionTx>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateSubscriptionRequest {
+    pub user: UserRef,
+    pub community_name: String,
+    /// Address of the new subscription wallet.
+    pub subscription_wallet: Address,
+    /// Signature of the subscription wallet message, created by the new subscription wallet.
+    pub subscription_wallet_signature: PackedEthSignature,
+    /// Pre-signed txs of the new subscription wallet.
+    pub txs: Vec<SubscriptionTx>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseItemRequest {
+    pub user: UserRef,
+    pub community_name: String,
+    pub item_id: String,
+    /// Hash of the zkSync transaction burning the item price.
+    pub tx_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitlementsRequest {
+    pub user: UserRef,
+    pub community_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeRequest {
+    /// Address of the user's main wallet.
+    pub address: Address,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginRequest {
+    pub address: Address,
+    /// Nonce of the challenge issued by the `/auth/challenge` endpoint.
+    pub nonce: String,
+    /// Signature of the challenge message, created by the main wallet.
+    pub signature: PackedEthSignature,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterUserRequest {
+    pub username: String,
+    /// Address of the user's main wallet.
+    pub address: Address,
+    /// Signature of the registration message, created by the main wallet.
+    pub signature: PackedEthSignature,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserLookupRequest {
+    pub user: UserRef,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubscribeRequest {
-    pub user: Address,
+    pub user: UserRef,
     pub community_name: String,
     pub subscription_wallet: Address,
+    /// Signature of the subscription wallet message, created by the subscription wallet.
+    /// Proves that the wallet belongs to user, so nobody can claim the payments of another user.
+    pub subscription_wallet_signature: PackedEthSignature,
     pub txs: Vec<SubscriptionTx>,
 }
@@ -0,0 +1,255 @@
+//! Helpers for the robust interaction with the external services the Service Provider depends on
+//! (Community Oracle and zkSync server).
+//!
+//! Every such service is represented by a `Dependency` object, which provides:
+//!
+//! - an HTTP client with the configured request timeout;
+//! - bounded retries with the exponential backoff (only for requests that are safe to repeat);
+//! - a circuit breaker, which makes requests fail fast once the service is considered down, and lets
+//!   a single trial request through once the reset timeout has passed.
+
+use crate::config::ResilienceConfig;
+use reqwest::StatusCode;
+use serde_derive::Serialize;
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+#[error("Service {dependency} is unavailable (circuit breaker is open)")]
+pub struct CircuitOpenError {
+    pub dependency: String,
+}
+
+/// Error type of the requests performed through the `Dependency`.
+pub trait DependencyError: From<CircuitOpenError> + std::fmt::Display {
+    /// Returns `true` if the error signals that the service is unavailable rather than that the request
+    /// was incorrect. Only such errors are retried and taken into account by the circuit breaker.
+    fn is_transient(&self) -> bool;
+}
+
+impl DependencyError for anyhow::Error {
+    fn is_transient(&self) -> bool {
+        // Network errors, timeouts and error HTTP statuses (the clients convert them with `error_for_status`)
+        // are reported by `reqwest`, while errors in the response content are created by the clients themselves.
+        match self.downcast_ref::<reqwest::Error>() {
+            Some(error) => match error.status() {
+                Some(status) => is_transient_status(status),
+                None => !error.is_decode(),
+            },
+            None => false,
+        }
+    }
+}
+
+/// Returns `true` if the HTTP status signals that the service failed or is overloaded,
+/// rather than that the request was incorrect.
+pub fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    /// Service is available, requests are passed through.
+    Closed,
+    /// Service is considered down, requests fail immediately.
+    Open,
+    /// Reset timeout has passed, a single trial request is passed through to check whether service is back.
+    /// Other requests fail immediately until the trial one succeeds.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// Moment the trial request was let through in the half-open state.
+    trial_started_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            failure_threshold,
+            reset_timeout,
+            state: Default::default(),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap();
+
+        self.current_state(&state)
+    }
+
+    fn current_state(&self, state: &BreakerState) -> CircuitState {
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.reset_timeout => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.state.lock().unwrap().consecutive_failures
+    }
+
+    /// Returns `true` if the request can be sent to the service. In the half-open state, only the first
+    /// caller is allowed to send the trial request, and its result must be recorded afterwards.
+    pub fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        match self.current_state(&state) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                // Trial request may never report its result (e.g. if its future was dropped),
+                // so it's considered lost after another reset period.
+                let trial_in_progress = state
+                    .trial_started_at
+                    .map(|started_at| started_at.elapsed() < self.reset_timeout)
+                    .unwrap_or(false);
+                if trial_in_progress {
+                    return false;
+                }
+
+                state.trial_started_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+        state.trial_started_at = None;
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        state.trial_started_at = None;
+
+        // Failed probe in the half-open state opens the circuit for another reset period as well.
+        if state.consecutive_failures >= self.failure_threshold {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Status of the dependency, reported by the health endpoint.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyStatus {
+    pub name: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Debug)]
+pub struct Dependency {
+    name: String,
+    config: ResilienceConfig,
+    breaker: CircuitBreaker,
+}
+
+impl Dependency {
+    pub fn new(name: impl Into<String>, config: ResilienceConfig) -> Self {
+        let breaker = CircuitBreaker::new(
+            config.circuit_breaker_threshold,
+            Duration::from_millis(config.circuit_breaker_reset_ms),
+        );
+
+        Self {
+            name: name.into(),
+            config,
+            breaker,
+        }
+    }
+
+    /// Creates an HTTP client with the configured request timeout.
+    pub fn http_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(Duration::from_millis(self.config.request_timeout_ms))
+            .build()
+            .expect("Unable to create HTTP client")
+    }
+
+    pub fn status(&self) -> DependencyStatus {
+        DependencyStatus {
+            name: self.name.clone(),
+            state: self.breaker.state(),
+            consecutive_failures: self.breaker.consecutive_failures(),
+        }
+    }
+
+    /// Performs the request to the service through the circuit breaker.
+    ///
+    /// If request is `idempotent`, it is retried on the transient errors (see `DependencyError::is_transient`)
+    /// with the exponential backoff. Requests that may have side effects are performed only once.
+    pub async fn call<T, E, F, Fut>(&self, idempotent: bool, request: F) -> Result<T, E>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: DependencyError,
+    {
+        let max_attempts = if idempotent {
+            self.config.max_retries + 1
+        } else {
+            1
+        };
+        let mut delay = Duration::from_millis(self.config.retry_base_delay_ms);
+        let mut attempt = 1;
+
+        loop {
+            if !self.breaker.allow_request() {
+                return Err(E::from(CircuitOpenError {
+                    dependency: self.name.clone(),
+                }));
+            }
+
+            match request().await {
+                Ok(response) => {
+                    self.breaker.record_success();
+                    return Ok(response);
+                }
+                Err(error) if error.is_transient() => {
+                    self.breaker.record_failure();
+
+                    if attempt >= max_attempts {
+                        return Err(error);
+                    }
+
+                    log::warn!(
+                        "Request to {} failed (attempt {} of {}), retrying in {:?}: {}",
+                        self.name,
+                        attempt,
+                        max_attempts,
+                        delay,
+                        error
+                    );
+                    tokio::time::delay_for(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(error) => {
+                    // Service has processed the request, so it's available.
+                    self.breaker.record_success();
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
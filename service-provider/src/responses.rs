@@ -2,9 +2,21 @@
 //! Note that some of types are re-exported from the `community-oracle` crate.
 //! See the `community_oracle::responses` module for their definitions.
 
+use crate::{
+    database::{Community, WebhookDelivery},
+    resilience::DependencyStatus,
+    zksync::{Address, SUBSCRIPTION_COST, SUBSCRIPTION_PERIOD_DAYS},
+};
 use chrono::{DateTime, Utc};
+use community_oracle::responses::GrantedTokensResponse as OracleGrantedTokensResponse;
 use serde_derive::{Deserialize, Serialize};
 
+pub use community_oracle::responses::{
+    BatchMintingSignatureResponse, ClaimStatusResponse, CustodialMintResponse,
+    GenesisWalletAddressResponse, MintingSignatureResponse, MintingSignatureVerificationResponse,
+    RegisterSigningKeyResponse, RelatedCommunitiesResponse,
+};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
@@ -19,10 +31,179 @@ impl ErrorResponse {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeResponse {
+    pub nonce: String,
+    /// Message to be signed by the main wallet.
+    pub message: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginResponse {
+    /// Session token, to be provided in the `Authorization: Bearer <token>` header.
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendSubscriptionResponse {
+    /// Amount of the txs added to the subscription.
+    pub added_txs: usize,
+    /// Amount of the txs which replaced the stored ones.
+    pub replaced_txs: usize,
+    /// Amount of the txs which were already stored.
+    pub skipped_txs: usize,
+    /// DateTime of the subscription period end, taking the new txs into account.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateSubscriptionResponse {
+    /// Time until which the subscription is paid by the previous subscription wallets.
+    pub carried_over_until: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubscriptionCheckResponse {
     pub subscribed: bool,
     pub started_at: Option<DateTime<Utc>>,
     pub expires_at: Option<DateTime<Utc>>,
+    /// Amount of the periods covered by the pre-signed txs which are not sent yet.
+    pub remaining_periods: usize,
+    /// `true` if user has to sign more subscription txs for the subscription to not lapse.
+    pub renewal_needed: bool,
+}
+
+/// Tokens granted by the Community Oracle, extended with the data known to the Service Provider.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantedTokensResponse {
+    #[serde(flatten)]
+    pub grant: OracleGrantedTokensResponse,
+    /// Address of the community ERC-20 token, if community is declared in the Service Provider.
+    pub token_address: Option<Address>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    pub healthy: bool,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// Differences between the community registries of the Service Provider and the Community Oracle.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunitiesReconciliationResponse {
+    /// Report for every oracle instance, since each of them keeps its own registry.
+    pub instances: Vec<InstanceReconciliation>,
+    /// `true` if the differences were fixed in every instance.
+    pub fixed: bool,
+}
+
+/// Differences between the registries of the Service Provider and a single oracle instance.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceReconciliation {
+    pub instance: String,
+    /// Communities declared in the Service Provider, but unknown to the oracle.
+    pub missing_in_oracle: Vec<String>,
+    /// Communities known to the oracle, but not declared in the Service Provider.
+    /// Built-in communities of the oracle are not included.
+    pub missing_in_provider: Vec<String>,
+    /// Communities which tokens differ between the registries.
+    pub mismatched: Vec<String>,
+    /// Built-in communities of the oracle. They are never removed from the oracle.
+    pub built_in: Vec<String>,
+    /// Error occurred while reconciling the instance, if any.
+    pub error: Option<String>,
+    /// `true` if the differences were fixed.
+    pub fixed: bool,
+}
+
+/// Subscription plan available for the community.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionPlan {
+    pub name: String,
+    /// Price of the single subscription period, in community tokens.
+    pub price: u64,
+    /// Symbol of the token used for payments.
+    pub token: String,
+    pub period_days: i64,
+}
+
+impl SubscriptionPlan {
+    /// Returns the plan available for every community.
+    pub fn default_for(community: &Community) -> Self {
+        Self {
+            name: "monthly".into(),
+            price: SUBSCRIPTION_COST,
+            token: community.erc20_token_name.clone(),
+            period_days: SUBSCRIPTION_PERIOD_DAYS,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityListItem {
+    #[serde(flatten)]
+    pub community: Community,
+    /// Community domain, e.g. `reddit.com/r/rust`. See `Community::domain`.
+    pub domain: String,
+    pub plans: Vec<SubscriptionPlan>,
+}
+
+impl CommunityListItem {
+    pub fn new(community: Community) -> Self {
+        Self {
+            domain: community.domain(),
+            plans: vec![SubscriptionPlan::default_for(&community)],
+            community,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityListResponse {
+    pub communities: Vec<CommunityListItem>,
+    /// Total amount of communities matching the filter.
+    pub total: usize,
+}
+
+/// Something user is allowed to do in the community.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Entitlement {
+    /// User has an active subscription.
+    #[serde(rename_all = "camelCase")]
+    Subscription { expires_at: Option<DateTime<Utc>> },
+    /// User owns the catalog item.
+    #[serde(rename_all = "camelCase")]
+    Item { item_id: String, quantity: usize },
+    /// User holds enough community tokens for the tier.
+    #[serde(rename_all = "camelCase")]
+    HoldingTier { tier: String, min_balance: u64 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitlementsResponse {
+    pub user: Address,
+    pub community_name: String,
+    pub entitlements: Vec<Entitlement>,
 }
@@ -1,97 +1,1438 @@
 use crate::{
-    config::AppConfig,
-    database::{DatabaseAccess, Subscription},
-    oracle::CommunityOracle,
+    admin::{Admin, AdminAuthenticator, AdminRole},
+    auth::{Authenticated, SessionManager},
+    config::{AppConfig, NotificationsConfig},
+    database::{
+        Community, CommunityError, CommunityFilter, DatabaseAccess, DeliveryStatus, Purchase,
+        Subscription, User, UserError,
+    },
+    notifications::{Notifier, SubscriptionEvent},
+    oracle::{CommunityOracle, OracleError},
     requests::{
-        DeclareCommunityRequest, GrantedTokensRequest, MintingSignatureRequest,
-        RelatedCommunitiesRequest, SubscribeRequest, SubscriptionCheckRequest,
+        AllowlistModeRequest, BatchMintingSignatureRequest, ChallengeRequest, ClaimStatusRequest,
+        CommunityListRequest, CommunityRequest, CommunityUserRequest, CustodialMintRequest,
+        DeclareCommunityRequest, EligibilityRulesRequest, EntitlementsRequest,
+        ExtendSubscriptionRequest, GrantedTokensRequest, LoginRequest, MigrateSubscriptionRequest,
+        MinKarmaRequest, MintingSignatureRequest, MintingSignatureVerificationRequest,
+        PurchaseItemRequest, ReconcileCommunitiesRequest, RegisterSigningKeyRequest,
+        RegisterUserRequest, RelatedCommunitiesRequest, ReplayWebhookDeliveriesRequest,
+        SubscribeRequest, SubscriptionCheckRequest, UpdateCommunityRequest, UserKarmaRequest,
+        UserLookupRequest, UserRef, WebhookDeliveriesRequest,
+    },
+    resilience::CircuitState,
+    responses::{
+        CommunitiesReconciliationResponse, CommunityListItem, CommunityListResponse, Entitlement,
+        EntitlementsResponse, ErrorResponse, ExtendSubscriptionResponse, GrantedTokensResponse,
+        HealthResponse, InstanceReconciliation, MigrateSubscriptionResponse,
+        SubscriptionCheckResponse, WebhookDeliveriesResponse,
+    },
+    utils::{response_from_db_error, response_from_error, response_from_oracle},
+    zksync::{
+        is_signed_by, Address, BurnTxError, PackedEthSignature, SubscriptionStatus, SubscriptionTx,
+        SubscriptionTxExt, ZksyncApp,
     },
-    responses::{ErrorResponse, SubscriptionCheckResponse},
-    utils::response_from_error,
-    zksync::ZksyncApp,
 };
 use actix_web::{web, HttpResponse, Scope};
 use anyhow::Result;
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use community_oracle::{
+    requests::{
+        BatchMintingSignatureRequest as OracleBatchMintingSignatureRequest,
+        DeclareCommunityRequest as OracleDeclareCommunityRequest, RemoveCommunityRequest,
+    },
+    responses::CommunityDescription,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// Amount of communities returned by `/communities` if limit is not specified.
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+/// Maximum amount of communities returned by `/communities` at once.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// Creates the message user signs to prove the ownership of the address on registration.
+fn registration_message(username: &str, address: Address) -> String {
+    format!(
+        "Link Reddit username {} to the address 0x{}",
+        username,
+        hex::encode(address.as_ref())
+    )
+}
+
+/// Creates the message the subscription wallet signs to prove that it belongs to user.
+fn subscription_wallet_message(
+    user: Address,
+    community_name: &str,
+    subscription_wallet: Address,
+) -> String {
+    format!(
+        "Use the wallet 0x{} for the subscription of 0x{} to the community {}",
+        hex::encode(subscription_wallet.as_ref()),
+        hex::encode(user.as_ref()),
+        community_name
+    )
+}
+
+/// Pre-signed txs provided for the subscription, sorted by their validity windows, see
+/// `ServiceProvider::sort_subscription_txs`.
+struct SortedSubscriptionTxs {
+    /// Txs for the periods which are not stored yet.
+    appended: Vec<SubscriptionTx>,
+    /// Txs replacing the stored ones for the same periods.
+    replacing: Vec<SubscriptionTx>,
+    /// Amount of the txs which are already stored.
+    skipped: usize,
+}
+
+impl SortedSubscriptionTxs {
+    fn into_txs(self) -> Vec<SubscriptionTx> {
+        let mut txs = self.appended;
+        txs.extend(self.replacing);
+        txs
+    }
+}
+
+/// Description of the community known to each Community Oracle instance before it was changed,
+/// along with the instance address. `None` means the instance didn't know the community.
+type OracleCommunityStates = Vec<(String, Option<CommunityDescription>)>;
+
+/// Creates a request to declare the community in the Community Oracle.
+fn oracle_declaration(description: CommunityDescription) -> OracleDeclareCommunityRequest {
+    OracleDeclareCommunityRequest {
+        community_name: description.name,
+        token_id: description.token_id,
+        token_symbol: description.token_symbol,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceProvider<DB: DatabaseAccess> {
+    db: Arc<DB>,
+    zksync: Arc<ZksyncApp>,
+    oracle: Arc<CommunityOracle>,
+    admin_authenticator: AdminAuthenticator,
+    sessions: SessionManager,
+    notifier: Notifier,
+    notifications: NotificationsConfig,
+}
+
+impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
+    pub fn new(db: DB, config: AppConfig) -> Self {
+        let oracle = CommunityOracle::new(&config);
+        let admin_authenticator = AdminAuthenticator::new(config.admin_api_keys);
+        let sessions = SessionManager::new(config.auth);
+        let notifier = Notifier::new(&config.notifications.sinks, config.resilience.clone());
+        let zksync = ZksyncApp::new(
+            config.zksync_rest_api_address,
+            config.zksync_json_rpc_address,
+            config.burn_account_address,
+            config.resilience,
+        );
+
+        Self {
+            db: Arc::new(db),
+            zksync: Arc::new(zksync),
+            oracle: Arc::new(oracle),
+            admin_authenticator,
+            sessions,
+            notifier,
+            notifications: config.notifications,
+        }
+    }
+
+    /// Returns `true` if the remaining pre-signed txs of the subscription cover fewer periods than configured.
+    fn renewal_needed(&self, subscription: &Subscription, now: DateTime<Utc>) -> bool {
+        subscription.remaining_periods(now) < self.notifications.renewal_threshold_periods
+    }
+
+    /// Records the event in the outbox and writes it to the log, unless the event was already emitted.
+    /// Errors are only logged, since they must not affect the operation that caused the event.
+    async fn emit(&self, event: SubscriptionEvent) {
+        let deliveries = self.notifier.deliveries(&event);
+
+        match self.db.add_event(&event.key(), deliveries).await {
+            Ok(true) => self.notifier.log(&event),
+            Ok(false) => {}
+            Err(error) => log::error!("Unable to record the event {:?}: {}", event, error),
+        }
+    }
+
+    /// Checks whether user is subscribed (see `ZksyncApp::check_subscription`), emitting the events
+    /// about the subscription payments sent during the check.
+    async fn check_subscription(&self, user: Address, subscription: Subscription) -> Result<bool> {
+        let community_name = subscription.service_name.clone();
+        let status = self.zksync.check_subscription(subscription).await?;
+
+        match &status {
+            SubscriptionStatus::Renewed(period_start) => {
+                self.emit(SubscriptionEvent::Renewed {
+                    user,
+                    community_name,
+                    period_start: *period_start,
+                })
+                .await
+            }
+            SubscriptionStatus::PaymentFailed {
+                period_start,
+                reason,
+            } => {
+                self.emit(SubscriptionEvent::PaymentFailed {
+                    user,
+                    community_name,
+                    period_start: *period_start,
+                    reason: reason.clone(),
+                })
+                .await
+            }
+            SubscriptionStatus::Active | SubscriptionStatus::Inactive => {}
+        }
+
+        Ok(status.is_subscribed())
+    }
+
+    /// Emits the `Expired` and `Cancelled` events for every lapsed subscription, and the `RenewalNeeded` event
+    /// for every active subscription which needs renewal. Subscriptions in the archived communities aren't reminded
+    /// about, since they can't be extended anyway.
+    async fn check_subscriptions(&self) -> Result<()> {
+        let now = Utc::now();
+
+        let archived_communities: Vec<_> = self
+            .db
+            .get_communities()
+            .await?
+            .into_iter()
+            .filter(|community| community.archived)
+            .map(|community| community.name)
+            .collect();
+
+        // Subscriptions are checked regardless of the community registry, since users may be subscribed
+        // to the communities which are not declared in the Service Provider.
+        for (user, subscription) in self.db.get_subscriptions().await? {
+            let expires_at = match subscription.expires_at() {
+                Some(expires_at) => expires_at,
+                // Subscription was never paid, so it can't lapse.
+                None => continue,
+            };
+            let archived = archived_communities.contains(&subscription.service_name);
+
+            if expires_at <= now {
+                self.emit(SubscriptionEvent::Expired {
+                    user,
+                    community_name: subscription.service_name.clone(),
+                    expired_at: expires_at,
+                })
+                .await;
+                self.emit(SubscriptionEvent::Cancelled {
+                    user,
+                    community_name: subscription.service_name.clone(),
+                    expires_at: Some(expires_at),
+                })
+                .await;
+            } else if !archived && self.renewal_needed(&subscription, now) {
+                self.emit(SubscriptionEvent::RenewalNeeded {
+                    user,
+                    community_name: subscription.service_name.clone(),
+                    remaining_periods: subscription.remaining_periods(now),
+                    expires_at,
+                })
+                .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Periodically checks the subscriptions which lapsed or need renewal, see `check_subscriptions`.
+    /// Must be spawned once on the application start.
+    pub async fn run_subscription_checks(self) {
+        let interval = Duration::from_secs(self.notifications.renewal_check_interval_secs);
+        let mut timer = tokio::time::interval(interval);
+
+        loop {
+            timer.tick().await;
+
+            if let Err(error) = self.check_subscriptions().await {
+                log::error!("Unable to check the subscriptions: {}", error);
+            }
+        }
+    }
+
+    /// Periodically probes the Community Oracle instances, see `CommunityOracle::run_health_probes`.
+    /// Must be spawned once on the application start.
+    pub async fn run_oracle_health_probes(self) {
+        self.oracle.clone().run_health_probes().await;
+    }
+
+    /// Attempts to send the pending webhook deliveries which are due. Failed deliveries are rescheduled
+    /// with the exponential backoff, until the configured amount of attempts is exhausted.
+    async fn deliver_webhooks(&self) -> Result<()> {
+        let now = Utc::now();
+        let base_delay =
+            chrono::Duration::seconds(self.notifications.webhook_retry_base_delay_secs as i64);
+
+        for mut delivery in self
+            .db
+            .get_webhook_deliveries(Some(DeliveryStatus::Pending))
+            .await?
+        {
+            if !delivery.is_due(now) {
+                continue;
+            }
+
+            match self.notifier.send(&delivery).await {
+                Ok(()) => delivery.record_success(),
+                Err(error) => {
+                    log::warn!(
+                        "Unable to deliver the event {} to {} (attempt {}): {}",
+                        delivery.event_key,
+                        delivery.url,
+                        delivery.attempts + 1,
+                        error
+                    );
+                    delivery.record_failure(
+                        error.to_string(),
+                        self.notifications.webhook_max_attempts,
+                        base_delay,
+                    );
+                }
+            }
+
+            self.db.update_webhook_delivery(delivery).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Periodically sends the pending webhook deliveries, see `deliver_webhooks`.
+    /// Must be spawned once on the application start.
+    pub async fn run_webhook_deliveries(self) {
+        let interval = Duration::from_secs(self.notifications.webhook_poll_interval_secs);
+        let mut timer = tokio::time::interval(interval);
+
+        loop {
+            timer.tick().await;
+
+            if let Err(error) = self.deliver_webhooks().await {
+                log::error!("Unable to deliver the webhooks: {}", error);
+            }
+        }
+    }
+
+    /// Declares the community in every Community Oracle instance, returning the previous description of the community
+    /// known to each instance. Communities are registered in the oracle before being stored, so tokens are never granted
+    /// for a community unknown to the Service Provider. If storing fails, oracle registry must be restored with
+    /// `restore_oracle_community`.
+    ///
+    /// If any instance fails, the instances which accepted the declaration are restored to their own previous state.
+    async fn declare_in_oracle(
+        &self,
+        community: &Community,
+    ) -> Result<OracleCommunityStates, OracleError> {
+        let declaration = oracle_declaration(community.oracle_description());
+        let responses = self.oracle.declare_community(declaration).await;
+
+        let mut previous = Vec::with_capacity(responses.len());
+        let mut first_error = None;
+        for (instance, response) in responses {
+            match response {
+                Ok(response) => previous.push((instance, response.previous)),
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => {
+                self.restore_oracle_community(&community.name, previous)
+                    .await;
+                Err(error)
+            }
+            None => Ok(previous),
+        }
+    }
+
+    /// Removes the community from every Community Oracle instance, returning the previous description of the community
+    /// for the instances it was removed from. Instances which don't know the community are skipped.
+    ///
+    /// If any instance fails, the instances the community was removed from are restored to their own previous state.
+    async fn remove_from_oracle(
+        &self,
+        community: &Community,
+    ) -> Result<OracleCommunityStates, OracleError> {
+        let request = RemoveCommunityRequest {
+            community_name: community.name.clone(),
+        };
+        let responses = self.oracle.remove_community(request).await;
+
+        let mut previous = Vec::with_capacity(responses.len());
+        let mut first_error = None;
+        for (instance, response) in responses {
+            match response {
+                Ok(()) => previous.push((instance, Some(community.oracle_description()))),
+                // Instance doesn't know the community, so there is nothing to remove.
+                Err(OracleError::Rejected(_)) => {}
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => {
+                self.restore_oracle_community(&community.name, previous)
+                    .await;
+                Err(error)
+            }
+            None => Ok(previous),
+        }
+    }
+
+    /// Restores every Community Oracle instance to its own previous state of the community after the failed operation.
+    /// Instances which can't be restored are reported one by one, since the oracle registry is left inconsistent.
+    async fn restore_oracle_community(
+        &self,
+        community_name: &str,
+        previous: OracleCommunityStates,
+    ) {
+        for (instance, previous) in previous {
+            let result = match previous {
+                Some(previous) => self
+                    .oracle
+                    .declare_instance_community(&instance, oracle_declaration(previous))
+                    .await
+                    .map(drop),
+                None => {
+                    let request = RemoveCommunityRequest {
+                        community_name: community_name.into(),
+                    };
+                    self.oracle
+                        .remove_instance_community(&instance, request)
+                        .await
+                }
+            };
+
+            if let Err(error) = result {
+                log::error!(
+                    "Unable to restore the community {} in the oracle {}, registries must be reconciled: {}",
+                    community_name,
+                    instance,
+                    error
+                );
+            }
+        }
+    }
+
+    /// Checks that the community accepts new subscription payments (i.e. it's not archived).
+    /// Returns the response to reject the request with, if it doesn't.
+    async fn check_community_accepts_payments(
+        &self,
+        community_name: &str,
+    ) -> Result<Option<HttpResponse>> {
+        let archived = self
+            .db
+            .get_community(community_name)
+            .await?
+            .map(|community| community.archived)
+            .unwrap_or(false);
+        if archived {
+            let response = HttpResponse::BadRequest().json(ErrorResponse::error(
+                "Community is archived and accepts no new subscriptions",
+            ));
+            return Ok(Some(response));
+        }
+
+        Ok(None)
+    }
+
+    /// Checks that the subscription wallet belongs to user, and that the community accepts new subscriptions.
+    /// Returns the response to reject the request with, if any check fails.
+    async fn check_subscription_wallet(
+        &self,
+        user: Address,
+        community_name: &str,
+        subscription_wallet: Address,
+        signature: &PackedEthSignature,
+    ) -> Result<Option<HttpResponse>> {
+        if let Some(response) = self
+            .check_community_accepts_payments(community_name)
+            .await?
+        {
+            return Ok(Some(response));
+        }
+
+        let message = subscription_wallet_message(user, community_name, subscription_wallet);
+        if !is_signed_by(&message, signature, subscription_wallet) {
+            let response = HttpResponse::BadRequest().json(ErrorResponse::error(
+                "Signature doesn't match the subscription wallet",
+            ));
+            return Ok(Some(response));
+        }
+
+        Ok(None)
+    }
+
+    /// Sorts the txs provided for the subscription by their validity windows: txs for the new periods are
+    /// appended to the stored series, and txs for the stored periods either repeat the stored ones or replace
+    /// them. Every tx must be correct, and the appended ones must continue the stored series. Returns
+    /// the response to reject the request with, if any check fails.
+    ///
+    /// Txs for the started periods can't be replaced, but this is checked by the database atomically.
+    async fn sort_subscription_txs(
+        &self,
+        subscription: &Subscription,
+        txs: Vec<SubscriptionTx>,
+    ) -> std::result::Result<SortedSubscriptionTxs, HttpResponse> {
+        let mut sorted = SortedSubscriptionTxs {
+            appended: Vec::new(),
+            replacing: Vec::new(),
+            skipped: 0,
+        };
+        for tx in txs {
+            let stored = subscription
+                .pre_signed_txs
+                .iter()
+                .find(|stored| stored.has_same_window(&tx));
+            let provided = sorted
+                .appended
+                .iter()
+                .chain(sorted.replacing.iter())
+                .find(|provided| provided.has_same_window(&tx));
+
+            match (stored, provided) {
+                (_, Some(provided)) if provided.is_same_as(&tx) => sorted.skipped += 1,
+                (_, Some(_)) => {
+                    return Err(HttpResponse::BadRequest().json(ErrorResponse::error(
+                        "Request contains different txs for the same period",
+                    )));
+                }
+                (Some(stored), None) if stored.is_same_as(&tx) => sorted.skipped += 1,
+                (Some(stored), None) => {
+                    if let Err(error) = self.zksync.check_subscription_tx_replacement(stored, &tx) {
+                        return Err(HttpResponse::BadRequest()
+                            .json(ErrorResponse::error(&error.to_string())));
+                    }
+                    sorted.replacing.push(tx);
+                }
+                (None, None) => sorted.appended.push(tx),
+            }
+        }
+
+        for subscription_tx in sorted.appended.iter().chain(sorted.replacing.iter()) {
+            if let Err(_) = self
+                .zksync
+                .check_subscription_tx(subscription, subscription_tx)
+                .await
+            {
+                return Err(HttpResponse::BadRequest()
+                    .json(ErrorResponse::error("Incorrect tx in request")));
+            }
+        }
+        if let Err(error) = self
+            .zksync
+            .check_subscription_txs_series(&subscription.pre_signed_txs, &sorted.appended)
+        {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::error(&error.to_string())));
+        }
+
+        Ok(sorted)
+    }
+
+    /// Resolves the user reference to the address of the main wallet.
+    async fn resolve_user(&self, user: &UserRef) -> Result<Address> {
+        match user {
+            UserRef::Address(address) => Ok(*address),
+            UserRef::Username(username) => match self.db.get_user_by_name(username).await? {
+                Some(user) => Ok(user.address),
+                None => Err(UserError::Unknown.into()),
+            },
+        }
+    }
+
+    pub async fn declare_community(
+        provider: web::Data<Self>,
+        request: Admin<DeclareCommunityRequest>,
+    ) -> Result<HttpResponse> {
+        let community = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request.community,
+            Err(response) => return Ok(response),
+        };
+
+        if let Err(error) = community.validate() {
+            return response_from_db_error(error.into());
+        }
+
+        if provider.db.get_community(&community.name).await?.is_some() {
+            return response_from_db_error(CommunityError::AlreadyDeclared.into());
+        }
+
+        let previous = match provider.declare_in_oracle(&community).await {
+            Ok(previous) => previous,
+            Err(OracleError::Rejected(error)) => return Ok(HttpResponse::BadRequest().json(error)),
+            Err(error) => return Err(error.into()),
+        };
+
+        let community_name = community.name.clone();
+        match provider.db.declare_community(community).await {
+            Ok(community) => Ok(HttpResponse::Ok().json(community)),
+            Err(error) => {
+                provider
+                    .restore_oracle_community(&community_name, previous)
+                    .await;
+                response_from_db_error(error)
+            }
+        }
+    }
+
+    /// Updates the community data. Request must contain the version of the community it's based on,
+    /// so concurrent modifications are detected rather than silently overwritten.
+    pub async fn update_community(
+        provider: web::Data<Self>,
+        request: Admin<UpdateCommunityRequest>,
+    ) -> Result<HttpResponse> {
+        let community = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request.community,
+            Err(response) => return Ok(response),
+        };
+
+        if let Err(error) = community.validate() {
+            return response_from_db_error(error.into());
+        }
+
+        let stored = match provider.db.get_community(&community.name).await? {
+            Some(stored) => stored,
+            None => return response_from_db_error(CommunityError::Unknown.into()),
+        };
+        if stored.version != community.version {
+            let error = CommunityError::VersionMismatch {
+                expected: community.version,
+                actual: stored.version,
+            };
+            return response_from_db_error(error.into());
+        }
+
+        let previous = match provider.declare_in_oracle(&community).await {
+            Ok(previous) => previous,
+            Err(OracleError::Rejected(error)) => return Ok(HttpResponse::BadRequest().json(error)),
+            Err(error) => return Err(error.into()),
+        };
+
+        let community_name = community.name.clone();
+        match provider.db.update_community(community).await {
+            Ok(community) => Ok(HttpResponse::Ok().json(community)),
+            Err(error) => {
+                provider
+                    .restore_oracle_community(&community_name, previous)
+                    .await;
+                response_from_db_error(error)
+            }
+        }
+    }
+
+    /// Archives the community: it accepts no new subscriptions, while existing ones are honored until they run out.
+    pub async fn archive_community(
+        provider: web::Data<Self>,
+        request: Admin<CommunityRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        match provider.db.archive_community(&request.community_name).await {
+            Ok(community) => Ok(HttpResponse::Ok().json(community)),
+            Err(error) => response_from_db_error(error),
+        }
+    }
+
+    /// Deletes the community from both the Service Provider and the Community Oracle.
+    /// Community can only be deleted if it has no active subscribers.
+    pub async fn delete_community(
+        provider: web::Data<Self>,
+        request: Admin<CommunityRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let community = match provider.db.get_community(&request.community_name).await? {
+            Some(community) => community,
+            None => return response_from_db_error(CommunityError::Unknown.into()),
+        };
+
+        // Check is read-only, so it never sends the pre-signed txs. It's repeated by the database
+        // atomically with the removal, and here it only allows to not touch the oracle in vain.
+        let now = Utc::now();
+        let subscriptions = provider
+            .db
+            .get_community_subscriptions(&community.name)
+            .await?;
+        if subscriptions
+            .iter()
+            .any(|(_, subscription)| subscription.is_active(now))
+        {
+            return response_from_db_error(CommunityError::HasActiveSubscribers.into());
+        }
+
+        let previous = provider.remove_from_oracle(&community).await?;
+
+        match provider.db.delete_community(&community.name).await {
+            Ok(removed) => {
+                // Subscriptions are removed along with the community, so they are never renewed.
+                for (user, subscription) in removed {
+                    provider
+                        .emit(SubscriptionEvent::Cancelled {
+                            user,
+                            community_name: community.name.clone(),
+                            expires_at: subscription.expires_at(),
+                        })
+                        .await;
+                }
+
+                Ok(HttpResponse::Ok().json(()))
+            }
+            Err(error) => {
+                provider
+                    .restore_oracle_community(&community.name, previous)
+                    .await;
+                response_from_db_error(error)
+            }
+        }
+    }
+
+    /// Compares the community registry of the Service Provider with the registry of every
+    /// Community Oracle instance. If requested, oracle registries are updated to match
+    /// the Service Provider one. Built-in communities of the oracle are reported, but never removed.
+    pub async fn reconcile_communities(
+        provider: web::Data<Self>,
+        request: Admin<ReconcileCommunitiesRequest>,
+    ) -> Result<HttpResponse> {
+        // Only the report is available to viewers, fixing the registry requires the operator role.
+        let required_role = if request.request().fix {
+            AdminRole::Operator
+        } else {
+            AdminRole::Viewer
+        };
+        let request = match request.authorize(required_role) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let provider_communities: HashMap<_, _> = provider
+            .db
+            .get_communities()
+            .await?
+            .iter()
+            .map(|community| (community.name.clone(), community.oracle_description()))
+            .collect();
+
+        let mut report = CommunitiesReconciliationResponse::default();
+        for instance in provider.oracle.instances() {
+            let mut instance_report = InstanceReconciliation {
+                instance,
+                ..Default::default()
+            };
+            if let Err(error) = provider
+                .reconcile_instance(&provider_communities, &mut instance_report, request.fix)
+                .await
+            {
+                log::warn!(
+                    "Unable to reconcile the communities of the oracle {}: {}",
+                    instance_report.instance,
+                    error
+                );
+                instance_report.error = Some(error.to_string());
+            }
+            report.instances.push(instance_report);
+        }
+        report.fixed = request.fix && report.instances.iter().all(|report| report.fixed);
+
+        Ok(HttpResponse::Ok().json(report))
+    }
+
+    /// Fills the report with the differences between the Service Provider registry and the registry
+    /// of a single oracle instance, fixing them if requested.
+    async fn reconcile_instance(
+        &self,
+        provider_communities: &HashMap<String, CommunityDescription>,
+        report: &mut InstanceReconciliation,
+        fix: bool,
+    ) -> Result<(), OracleError> {
+        let response = self.oracle.instance_communities(&report.instance).await?;
+        let oracle_communities: HashMap<_, _> = response
+            .communities
+            .into_iter()
+            .map(|community| (community.name.clone(), community))
+            .collect();
+
+        for (name, description) in provider_communities {
+            match oracle_communities.get(name) {
+                None => report.missing_in_oracle.push(name.clone()),
+                Some(oracle_description) if oracle_description != description => {
+                    report.mismatched.push(name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        report.missing_in_provider = oracle_communities
+            .keys()
+            .filter(|name| !provider_communities.contains_key(*name))
+            .filter(|name| !response.built_in.contains(*name))
+            .cloned()
+            .collect();
+        report.built_in = response.built_in;
+
+        report.missing_in_oracle.sort();
+        report.missing_in_provider.sort();
+        report.mismatched.sort();
+        report.built_in.sort();
+
+        if fix {
+            for name in report.missing_in_oracle.iter().chain(&report.mismatched) {
+                let declaration = oracle_declaration(provider_communities[name].clone());
+                self.oracle
+                    .declare_instance_community(&report.instance, declaration)
+                    .await?;
+            }
+            for name in &report.missing_in_provider {
+                let request = RemoveCommunityRequest {
+                    community_name: name.clone(),
+                };
+                self.oracle
+                    .remove_instance_community(&report.instance, request)
+                    .await?;
+            }
+            report.fixed = true;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the eligibility rules of the community. Only available with the embedded Community Oracle.
+    pub async fn eligibility_rules(
+        provider: web::Data<Self>,
+        request: Admin<EligibilityRulesRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Viewer) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let result = provider.oracle.eligibility_rules(request).await;
+
+        response_from_oracle(result)
+    }
+
+    /// Bans user in the community. Only available with the embedded Community Oracle.
+    pub async fn ban_user(
+        provider: web::Data<Self>,
+        request: Admin<CommunityUserRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .ban_user(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    /// Removes user from the ban list of the community. Only available with the embedded Community Oracle.
+    pub async fn unban_user(
+        provider: web::Data<Self>,
+        request: Admin<CommunityUserRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .unban_user(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    /// Enables or disables the allowlist mode of the community. Only available with the embedded Community Oracle.
+    pub async fn set_allowlist_mode(
+        provider: web::Data<Self>,
+        request: Admin<AllowlistModeRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let result = provider.oracle.set_allowlist_mode(request).await;
+
+        response_from_oracle(result)
+    }
+
+    /// Adds user to the allowlist of the community. Only available with the embedded Community Oracle.
+    pub async fn allow_user(
+        provider: web::Data<Self>,
+        request: Admin<CommunityUserRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .allow_user(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    /// Removes user from the allowlist of the community. Only available with the embedded Community Oracle.
+    pub async fn disallow_user(
+        provider: web::Data<Self>,
+        request: Admin<CommunityUserRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .disallow_user(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    /// Sets the minimum karma required for the round in the community. Only available with the embedded Community Oracle.
+    pub async fn set_min_karma(
+        provider: web::Data<Self>,
+        request: Admin<MinKarmaRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let result = provider.oracle.set_min_karma(request).await;
+
+        response_from_oracle(result)
+    }
+
+    /// Sets the karma of user in the community. Only available with the embedded Community Oracle.
+    pub async fn set_user_karma(
+        provider: web::Data<Self>,
+        request: Admin<UserKarmaRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .set_user_karma(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    /// Lists the communities known to the Service Provider along with their subscription plans.
+    pub async fn communities(
+        provider: web::Data<Self>,
+        request: web::Json<CommunityListRequest>,
+    ) -> Result<HttpResponse> {
+        let request = request.into_inner();
+
+        let filter = CommunityFilter {
+            name_prefix: request.name_prefix,
+            token: request.token,
+            include_archived: request.include_archived,
+            offset: request.offset,
+            limit: request
+                .limit
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .min(MAX_PAGE_SIZE),
+        };
+        let (communities, total) = provider.db.list_communities(&filter).await?;
+
+        let communities = communities
+            .into_iter()
+            .map(CommunityListItem::new)
+            .collect();
+
+        Ok(HttpResponse::Ok().json(CommunityListResponse { communities, total }))
+    }
+
+    /// Returns the community known to the Service Provider along with its subscription plans.
+    pub async fn community(
+        provider: web::Data<Self>,
+        request: web::Json<CommunityRequest>,
+    ) -> Result<HttpResponse> {
+        let request = request.into_inner();
+
+        match provider.db.get_community(&request.community_name).await? {
+            Some(community) => Ok(HttpResponse::Ok().json(CommunityListItem::new(community))),
+            None => response_from_db_error(CommunityError::Unknown.into()),
+        }
+    }
+
+    pub async fn related_communities(
+        provider: web::Data<Self>,
+        request: web::Json<RelatedCommunitiesRequest>,
+    ) -> Result<HttpResponse> {
+        let request = request.into_inner();
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .related_communities(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    pub async fn subscribe(
+        provider: web::Data<Self>,
+        request: Authenticated<SubscribeRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        if let Some(response) = provider
+            .check_subscription_wallet(
+                user,
+                &request.community_name,
+                request.subscription_wallet,
+                &request.subscription_wallet_signature,
+            )
+            .await?
+        {
+            return Ok(response);
+        }
+
+        // Repeated subscription with the same wallet is validated against the stored txs the same way
+        // as `extend_subscription` does.
+        let subscription = match provider
+            .db
+            .get_subscription(user, &request.community_name)
+            .await?
+        {
+            Some(existing) if existing.subscription_wallet != request.subscription_wallet => {
+                let response = HttpResponse::BadRequest().json(ErrorResponse::error(
+                    "User is subscribed with another subscription wallet, use `/migrate_subscription` to replace it",
+                ));
+                return Ok(response);
+            }
+            Some(existing) => existing,
+            None => Subscription::new(&request.community_name, request.subscription_wallet),
+        };
+        let txs = match provider
+            .sort_subscription_txs(&subscription, request.txs)
+            .await
+        {
+            Ok(sorted) => sorted.into_txs(),
+            Err(response) => return Ok(response),
+        };
+
+        let subscription = Subscription::new(&request.community_name, request.subscription_wallet);
+        provider.db.add_subscription(user, subscription).await?;
+
+        if let Err(error) = provider
+            .db
+            .add_subscription_txs(user, &request.community_name, txs)
+            .await
+        {
+            return response_from_db_error(error);
+        }
+
+        provider
+            .emit(SubscriptionEvent::Created {
+                user,
+                community_name: request.community_name,
+                subscription_wallet: request.subscription_wallet,
+            })
+            .await;
+
+        let response = HttpResponse::Ok().json(());
+
+        Ok(response)
+    }
+
+    /// Appends the pre-signed txs for the next subscription periods to the existing subscription.
+    /// Txs which are already stored are skipped, txs for the periods that haven't started yet replace
+    /// the stored ones, and the rest must continue the stored series.
+    pub async fn extend_subscription(
+        provider: web::Data<Self>,
+        request: Authenticated<ExtendSubscriptionRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let subscription = match provider
+            .db
+            .get_subscription(user, &request.community_name)
+            .await?
+        {
+            Some(subscription) => subscription,
+            None => {
+                let response = HttpResponse::BadRequest().json(ErrorResponse::error(
+                    "User is not subscribed to the community",
+                ));
+                return Ok(response);
+            }
+        };
+        if let Some(response) = provider
+            .check_community_accepts_payments(&request.community_name)
+            .await?
+        {
+            return Ok(response);
+        }
+
+        let sorted = match provider
+            .sort_subscription_txs(&subscription, request.txs)
+            .await
+        {
+            Ok(sorted) => sorted,
+            Err(response) => return Ok(response),
+        };
+        let added_txs = sorted.appended.len();
+        let replaced_txs = sorted.replacing.len();
+        let skipped_txs = sorted.skipped;
+        let txs = sorted.into_txs();
+
+        // Txs for the started periods can't be replaced, this is checked by the database atomically.
+        if let Err(error) = provider
+            .db
+            .add_subscription_txs(user, &request.community_name, txs)
+            .await
+        {
+            return response_from_db_error(error);
+        }
+
+        let expires_at = match provider
+            .db
+            .get_subscription(user, &request.community_name)
+            .await?
+        {
+            Some(subscription) => {
+                provider
+                    .zksync
+                    .get_subscription_period(subscription)
+                    .await?
+                    .1
+            }
+            None => None,
+        };
+
+        Ok(HttpResponse::Ok().json(ExtendSubscriptionResponse {
+            added_txs,
+            replaced_txs,
+            skipped_txs,
+            expires_at,
+        }))
+    }
+
+    /// Replaces the subscription wallet of user (e.g. after the subscription was cancelled by rotating the key).
+    /// Time paid by the previous wallet is carried over, and the pre-signed txs of the new wallet become active.
+    pub async fn migrate_subscription(
+        provider: web::Data<Self>,
+        request: Authenticated<MigrateSubscriptionRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
 
-#[derive(Debug, Clone)]
-pub struct ServiceProvider<DB: DatabaseAccess> {
-    db: Arc<DB>,
-    zksync: Arc<ZksyncApp>,
-    oracle: Arc<CommunityOracle>,
-}
+        let subscription = match provider
+            .db
+            .get_subscription(user, &request.community_name)
+            .await?
+        {
+            Some(subscription) => subscription,
+            None => {
+                let response = HttpResponse::BadRequest().json(ErrorResponse::error(
+                    "User is not subscribed to the community",
+                ));
+                return Ok(response);
+            }
+        };
+        if subscription.subscription_wallet == request.subscription_wallet {
+            let response = HttpResponse::BadRequest().json(ErrorResponse::error(
+                "New subscription wallet must differ from the current one",
+            ));
+            return Ok(response);
+        }
 
-impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
-    pub fn new(db: DB, config: AppConfig) -> Self {
-        let zksync = ZksyncApp::new(
-            config.zksync_rest_api_address,
-            config.zksync_json_rpc_address,
-            config.burn_account_address,
-        );
-        let oracle = CommunityOracle::new(config.community_oracle_address);
+        if let Some(response) = provider
+            .check_subscription_wallet(
+                user,
+                &request.community_name,
+                request.subscription_wallet,
+                &request.subscription_wallet_signature,
+            )
+            .await?
+        {
+            return Ok(response);
+        }
 
-        Self {
-            db: Arc::new(db),
-            zksync: Arc::new(zksync),
-            oracle: Arc::new(oracle),
+        // New wallet starts its own series of txs, so there are no stored txs to continue.
+        let new_subscription =
+            Subscription::new(&request.community_name, request.subscription_wallet);
+        let txs = match provider
+            .sort_subscription_txs(&new_subscription, request.txs)
+            .await
+        {
+            Ok(sorted) => sorted.into_txs(),
+            Err(response) => return Ok(response),
+        };
+
+        let paid_until = provider.zksync.paid_until(&subscription).await?;
+        provider
+            .db
+            .migrate_subscription(
+                user,
+                &request.community_name,
+                request.subscription_wallet,
+                paid_until,
+                txs,
+            )
+            .await?;
+
+        // Pre-signed txs of the previous wallet are dropped, so they are never sent.
+        provider
+            .emit(SubscriptionEvent::Cancelled {
+                user,
+                community_name: request.community_name.clone(),
+                expires_at: subscription.expires_at(),
+            })
+            .await;
+        provider
+            .emit(SubscriptionEvent::WalletMigrated {
+                user,
+                community_name: request.community_name,
+                previous_wallet: subscription.subscription_wallet,
+                subscription_wallet: request.subscription_wallet,
+            })
+            .await;
+
+        Ok(HttpResponse::Ok().json(MigrateSubscriptionResponse {
+            carried_over_until: paid_until,
+        }))
+    }
+
+    /// Lists the webhook deliveries stored in the outbox.
+    pub async fn webhook_deliveries(
+        provider: web::Data<Self>,
+        request: Admin<WebhookDeliveriesRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Viewer) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let deliveries = provider.db.get_webhook_deliveries(request.status).await?;
+
+        Ok(HttpResponse::Ok().json(WebhookDeliveriesResponse { deliveries }))
+    }
+
+    /// Schedules the webhook deliveries (e.g. the failed ones) to be attempted again.
+    pub async fn replay_webhook_deliveries(
+        provider: web::Data<Self>,
+        request: Admin<ReplayWebhookDeliveriesRequest>,
+    ) -> Result<HttpResponse> {
+        let request = match request.authorize(AdminRole::Operator) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        match provider.db.replay_webhook_deliveries(&request.ids).await {
+            Ok(deliveries) => Ok(HttpResponse::Ok().json(WebhookDeliveriesResponse { deliveries })),
+            Err(error) => response_from_db_error(error),
         }
     }
 
-    pub async fn declare_community(
+    /// Issues the login challenge to be signed by the main wallet of user.
+    pub async fn challenge(
         provider: web::Data<Self>,
-        request: web::Json<DeclareCommunityRequest>,
+        request: web::Json<ChallengeRequest>,
     ) -> Result<HttpResponse> {
         let request = request.into_inner();
 
-        provider.db.declare_community(request.community).await?;
+        let response = provider.sessions.challenge(request.address);
 
-        Ok(HttpResponse::Ok().json(()))
+        Ok(HttpResponse::Ok().json(response))
     }
 
-    pub async fn related_communities(
+    /// Opens the session for user who signed the login challenge.
+    pub async fn login(
         provider: web::Data<Self>,
-        request: web::Json<RelatedCommunitiesRequest>,
+        request: web::Json<LoginRequest>,
     ) -> Result<HttpResponse> {
         let request = request.into_inner();
 
-        let response = provider.oracle.related_communities(request).await?;
+        match provider
+            .sessions
+            .login(request.address, &request.nonce, &request.signature)
+        {
+            Ok(response) => Ok(HttpResponse::Ok().json(response)),
+            Err(error) => {
+                Ok(HttpResponse::Unauthorized().json(ErrorResponse::error(&error.to_string())))
+            }
+        }
+    }
 
-        Ok(response)
+    /// Links the Reddit username to the main wallet address. User proves the ownership of the address
+    /// by signing the registration message.
+    pub async fn register_user(
+        provider: web::Data<Self>,
+        request: web::Json<RegisterUserRequest>,
+    ) -> Result<HttpResponse> {
+        let request = request.into_inner();
+
+        if !User::is_valid_username(&request.username) {
+            return response_from_db_error(UserError::InvalidUsername.into());
+        }
+
+        let message = registration_message(&request.username, request.address);
+        if !is_signed_by(&message, &request.signature, request.address) {
+            let response = HttpResponse::BadRequest()
+                .json(ErrorResponse::error("Signature doesn't match the address"));
+            return Ok(response);
+        }
+
+        let user = User {
+            username: request.username,
+            address: request.address,
+            registered_at: Utc::now(),
+        };
+        match provider.db.register_user(user).await {
+            Ok(user) => Ok(HttpResponse::Ok().json(user)),
+            Err(error) => response_from_db_error(error),
+        }
     }
 
-    pub async fn subscribe(
+    /// Looks up the registered user either by the username or by the address.
+    pub async fn user(
         provider: web::Data<Self>,
-        request: web::Json<SubscribeRequest>,
+        request: web::Json<UserLookupRequest>,
     ) -> Result<HttpResponse> {
         let request = request.into_inner();
 
-        let subscription = Subscription::new(&request.community_name, request.subscription_wallet);
+        let user = match request.user {
+            UserRef::Address(address) => provider.db.get_user_by_address(address).await?,
+            UserRef::Username(username) => provider.db.get_user_by_name(&username).await?,
+        };
 
-        for subscription_tx in &request.txs {
-            if let Err(_) = provider
-                .zksync
-                .check_subscription_tx(&subscription, subscription_tx)
-                .await
-            {
+        match user {
+            Some(user) => Ok(HttpResponse::Ok().json(user)),
+            None => response_from_db_error(UserError::Unknown.into()),
+        }
+    }
+
+    /// Buys the catalog item for the tokens burnt by user.
+    pub async fn purchase_item(
+        provider: web::Data<Self>,
+        request: Authenticated<PurchaseItemRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let community = match provider.db.get_community(&request.community_name).await? {
+            Some(community) => community,
+            None => return response_from_db_error(CommunityError::Unknown.into()),
+        };
+        let item = match community.item(&request.item_id) {
+            Some(item) => item,
+            None => {
                 let response = HttpResponse::BadRequest()
-                    .json(ErrorResponse::error("Incorrect tx in request"));
+                    .json(ErrorResponse::error("Item is not in the community catalog"));
                 return Ok(response);
             }
-        }
-
-        provider
-            .db
-            .add_subscription(request.user, subscription)
-            .await?;
+        };
 
-        provider
-            .db
-            .add_subscription_txs(request.user, &request.community_name, request.txs)
-            .await?;
+        let tx_hash = match provider
+            .zksync
+            .check_burn_tx(
+                user,
+                &request.tx_hash,
+                community.zksync_token_id,
+                &community.token_base_units(item.price),
+            )
+            .await
+        {
+            Ok(tx_hash) => tx_hash,
+            Err(error) if error.downcast_ref::<BurnTxError>().is_some() => {
+                let response =
+                    HttpResponse::BadRequest().json(ErrorResponse::error(&error.to_string()));
+                return Ok(response);
+            }
+            Err(error) => return Err(error),
+        };
 
-        let response = HttpResponse::Ok().json(());
+        let purchase = Purchase {
+            community_name: community.name.clone(),
+            item_id: item.id.clone(),
+            price: item.price,
+            tx_hash,
+            purchased_at: Utc::now(),
+        };
+        if let Err(error) = provider.db.add_purchase(user, purchase.clone()).await {
+            return response_from_db_error(error);
+        }
 
-        Ok(response)
+        Ok(HttpResponse::Ok().json(purchase))
     }
 
     pub async fn tokens_for_user(
@@ -99,10 +1440,33 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
         request: web::Json<GrantedTokensRequest>,
     ) -> Result<HttpResponse> {
         let request = request.into_inner();
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
 
-        let response = provider.oracle.tokens_for_user(request).await?;
+        let community_name = request.community_name.clone();
 
-        Ok(response)
+        let grant = match provider
+            .oracle
+            .tokens_for_user(request.into_oracle_request(user))
+            .await
+        {
+            Ok(grant) => grant,
+            Err(OracleError::Rejected(error)) => return Ok(HttpResponse::BadRequest().json(error)),
+            Err(error) => return Err(error.into()),
+        };
+
+        let token_address = provider
+            .db
+            .get_community(&community_name)
+            .await?
+            .map(|community| community.erc20_token_address);
+
+        Ok(HttpResponse::Ok().json(GrantedTokensResponse {
+            grant,
+            token_address,
+        }))
     }
 
     pub async fn sign_minting_tx(
@@ -110,21 +1474,129 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
         request: web::Json<MintingSignatureRequest>,
     ) -> Result<HttpResponse> {
         let request = request.into_inner();
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
 
-        let response = provider.oracle.sign_minting_tx(request).await?;
+        let result = provider
+            .oracle
+            .sign_minting_tx(request.into_oracle_request(user))
+            .await;
 
-        Ok(response)
+        response_from_oracle(result)
     }
 
-    pub async fn is_user_subscribed(
+    pub async fn sign_minting_txs_batch(
+        provider: web::Data<Self>,
+        request: web::Json<BatchMintingSignatureRequest>,
+    ) -> Result<HttpResponse> {
+        let request = request.into_inner();
+
+        let mut requests = Vec::with_capacity(request.requests.len());
+        for request in request.requests {
+            let user = match provider.resolve_user(&request.user).await {
+                Ok(user) => user,
+                Err(error) => return response_from_db_error(error),
+            };
+            requests.push(request.into_oracle_request(user));
+        }
+
+        let result = provider
+            .oracle
+            .sign_minting_txs_batch(OracleBatchMintingSignatureRequest { requests })
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    pub async fn verify_minting_signature(
         provider: web::Data<Self>,
-        request: web::Json<SubscriptionCheckRequest>,
+        request: web::Json<MintingSignatureVerificationRequest>,
     ) -> Result<HttpResponse> {
         let request = request.into_inner();
 
+        let result = provider.oracle.verify_minting_signature(request).await;
+
+        response_from_oracle(result)
+    }
+
+    pub async fn claim_status(
+        provider: web::Data<Self>,
+        request: web::Json<ClaimStatusRequest>,
+    ) -> Result<HttpResponse> {
+        let request = request.into_inner();
+        let user = match provider.resolve_user(&request.user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+
+        let result = provider
+            .oracle
+            .claim_status(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    pub async fn register_signing_key(
+        provider: web::Data<Self>,
+        request: Authenticated<RegisterSigningKeyRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let result = provider
+            .oracle
+            .register_signing_key(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    pub async fn custodial_mint(
+        provider: web::Data<Self>,
+        request: Authenticated<CustodialMintRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let result = provider
+            .oracle
+            .custodial_mint(request.into_oracle_request(user))
+            .await;
+
+        response_from_oracle(result)
+    }
+
+    pub async fn is_user_subscribed(
+        provider: web::Data<Self>,
+        request: Authenticated<SubscriptionCheckRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize_status_check(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
         let sub = match provider
             .db
-            .get_subscription(request.user, &request.community_name)
+            .get_subscription(user, &request.community_name)
             .await?
         {
             Some(community) => community,
@@ -133,29 +1605,137 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
                     subscribed: false,
                     started_at: None,
                     expires_at: None,
+                    remaining_periods: 0,
+                    renewal_needed: false,
                 }))
             }
         };
 
-        let subscribed = provider.zksync.is_user_subscribed(sub.clone()).await?;
+        let now = Utc::now();
+        let remaining_periods = sub.remaining_periods(now);
+        let renewal_needed = provider.renewal_needed(&sub, now);
+
+        let subscribed = provider.check_subscription(user, sub.clone()).await?;
         let (started_at, expires_at) = provider.zksync.get_subscription_period(sub).await?;
 
         Ok(HttpResponse::Ok().json(SubscriptionCheckResponse {
             subscribed,
             started_at,
             expires_at,
+            remaining_periods,
+            // Lapsed subscription has to be renewed as well, but there is no point in reminding about it.
+            renewal_needed: subscribed && renewal_needed,
+        }))
+    }
+
+    /// Aggregates everything user is allowed to do in the community: active subscription, owned items
+    /// and tiers granted for holding the community tokens.
+    pub async fn entitlements(
+        provider: web::Data<Self>,
+        request: Authenticated<EntitlementsRequest>,
+    ) -> Result<HttpResponse> {
+        let user = match provider.resolve_user(&request.request().user).await {
+            Ok(user) => user,
+            Err(error) => return response_from_db_error(error),
+        };
+        let request = match request.authorize_status_check(user) {
+            Ok(request) => request,
+            Err(response) => return Ok(response),
+        };
+
+        let community = match provider.db.get_community(&request.community_name).await? {
+            Some(community) => community,
+            None => return response_from_db_error(CommunityError::Unknown.into()),
+        };
+        let mut entitlements = Vec::new();
+
+        if let Some(sub) = provider.db.get_subscription(user, &community.name).await? {
+            if provider.check_subscription(user, sub.clone()).await? {
+                let (_, expires_at) = provider.zksync.get_subscription_period(sub).await?;
+                entitlements.push(Entitlement::Subscription { expires_at });
+            }
+        }
+
+        // Items are counted in the order of the first purchase.
+        let mut owned_items: Vec<(String, usize)> = Vec::new();
+        for purchase in provider.db.get_user_purchases(user).await? {
+            if purchase.community_name != community.name {
+                continue;
+            }
+            match owned_items
+                .iter_mut()
+                .find(|(id, _)| *id == purchase.item_id)
+            {
+                Some((_, quantity)) => *quantity += 1,
+                None => owned_items.push((purchase.item_id, 1)),
+            }
+        }
+        entitlements.extend(
+            owned_items
+                .into_iter()
+                .map(|(item_id, quantity)| Entitlement::Item { item_id, quantity }),
+        );
+
+        if !community.holding_tiers.is_empty() {
+            let balance = provider
+                .zksync
+                .token_balance(user, &community.erc20_token_name)
+                .await?;
+
+            entitlements.extend(
+                community
+                    .holding_tiers
+                    .iter()
+                    .filter(|tier| balance >= community.token_base_units(tier.min_balance))
+                    .map(|tier| Entitlement::HoldingTier {
+                        tier: tier.name.clone(),
+                        min_balance: tier.min_balance,
+                    }),
+            );
+        }
+
+        Ok(HttpResponse::Ok().json(EntitlementsResponse {
+            user,
+            community_name: community.name,
+            entitlements,
         }))
     }
 
     pub async fn genesis_wallet_address(provider: web::Data<Self>) -> HttpResponse {
-        let response = provider.oracle.genesis_wallet_address(()).await;
+        let result = provider.oracle.genesis_wallet_address().await;
 
-        match response {
+        match response_from_oracle(result) {
             Ok(response) => response,
             Err(error) => response_from_error(error),
         }
     }
 
+    /// Reports the state of the services the Service Provider depends on.
+    /// Application is considered healthy if at least one oracle instance is available,
+    /// and none of the zkSync circuit breakers is open.
+    pub async fn health(provider: web::Data<Self>) -> HttpResponse {
+        let oracle_status = provider.oracle.status();
+        let zksync_status = provider.zksync.status();
+
+        let healthy = provider.oracle.is_available()
+            && zksync_status
+                .iter()
+                .all(|dependency| dependency.state != CircuitState::Open);
+
+        let mut dependencies = oracle_status;
+        dependencies.extend(zksync_status);
+        let response = HealthResponse {
+            healthy,
+            dependencies,
+        };
+
+        if healthy {
+            HttpResponse::Ok().json(response)
+        } else {
+            HttpResponse::ServiceUnavailable().json(response)
+        }
+    }
+
     /// Wrapper around functions that return `anyhow::Result` which converts it to the `HttpResponse`.
     /// This decorator allows handler functions to return `Result` and use `?` for convenient error propagation.
     ///
@@ -163,10 +1743,10 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
     pub async fn failable<F, Fut, R>(
         handler: F,
         provider: web::Data<Self>,
-        request: web::Json<R>,
+        request: R,
     ) -> HttpResponse
     where
-        F: Fn(web::Data<Self>, web::Json<R>) -> Fut,
+        F: Fn(web::Data<Self>, R) -> Fut,
         Fut: std::future::Future<Output = Result<HttpResponse>>,
     {
         match handler(provider, request).await {
@@ -175,13 +1755,92 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
         }
     }
 
-    pub fn into_web_scope(self) -> Scope {
-        web::scope("api/v0.1/")
+    /// Creates the scope of the private API. It must be served on a separate bind address,
+    /// which is not exposed to the public.
+    pub fn into_admin_scope(self) -> Scope {
+        web::scope("admin/v0.1/")
+            .data(self.admin_authenticator.clone())
             .data(self)
             .service(
                 web::resource("/declare_community")
                     .to(|p, data| Self::failable(Self::declare_community, p, data)),
             )
+            .service(
+                web::resource("/update_community")
+                    .to(|p, data| Self::failable(Self::update_community, p, data)),
+            )
+            .service(
+                web::resource("/archive_community")
+                    .to(|p, data| Self::failable(Self::archive_community, p, data)),
+            )
+            .service(
+                web::resource("/delete_community")
+                    .to(|p, data| Self::failable(Self::delete_community, p, data)),
+            )
+            .service(
+                web::resource("/reconcile_communities")
+                    .to(|p, data| Self::failable(Self::reconcile_communities, p, data)),
+            )
+            .service(
+                web::resource("/webhook_deliveries")
+                    .to(|p, data| Self::failable(Self::webhook_deliveries, p, data)),
+            )
+            .service(
+                web::resource("/replay_webhook_deliveries")
+                    .to(|p, data| Self::failable(Self::replay_webhook_deliveries, p, data)),
+            )
+            .service(
+                web::resource("/oracle/eligibility_rules")
+                    .to(|p, data| Self::failable(Self::eligibility_rules, p, data)),
+            )
+            .service(
+                web::resource("/oracle/ban_user")
+                    .to(|p, data| Self::failable(Self::ban_user, p, data)),
+            )
+            .service(
+                web::resource("/oracle/unban_user")
+                    .to(|p, data| Self::failable(Self::unban_user, p, data)),
+            )
+            .service(
+                web::resource("/oracle/set_allowlist_mode")
+                    .to(|p, data| Self::failable(Self::set_allowlist_mode, p, data)),
+            )
+            .service(
+                web::resource("/oracle/allow_user")
+                    .to(|p, data| Self::failable(Self::allow_user, p, data)),
+            )
+            .service(
+                web::resource("/oracle/disallow_user")
+                    .to(|p, data| Self::failable(Self::disallow_user, p, data)),
+            )
+            .service(
+                web::resource("/oracle/set_min_karma")
+                    .to(|p, data| Self::failable(Self::set_min_karma, p, data)),
+            )
+            .service(
+                web::resource("/oracle/set_user_karma")
+                    .to(|p, data| Self::failable(Self::set_user_karma, p, data)),
+            )
+    }
+
+    pub fn into_web_scope(self) -> Scope {
+        web::scope("api/v0.1/")
+            .data(self.sessions.clone())
+            .data(self)
+            .service(
+                web::resource("/auth/challenge")
+                    .to(|p, data| Self::failable(Self::challenge, p, data)),
+            )
+            .service(
+                web::resource("/auth/login").to(|p, data| Self::failable(Self::login, p, data)),
+            )
+            .service(
+                web::resource("/community").to(|p, data| Self::failable(Self::community, p, data)),
+            )
+            .service(
+                web::resource("/communities")
+                    .to(|p, data| Self::failable(Self::communities, p, data)),
+            )
             .service(
                 web::resource("/is_user_subscribed")
                     .to(|p, data| Self::failable(Self::is_user_subscribed, p, data)),
@@ -190,6 +1849,26 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
                 web::resource("/get_minting_signature")
                     .to(|p, data| Self::failable(Self::sign_minting_tx, p, data)),
             )
+            .service(
+                web::resource("/get_minting_signatures_batch")
+                    .to(|p, data| Self::failable(Self::sign_minting_txs_batch, p, data)),
+            )
+            .service(
+                web::resource("/verify_minting_signature")
+                    .to(|p, data| Self::failable(Self::verify_minting_signature, p, data)),
+            )
+            .service(
+                web::resource("/claim_status")
+                    .to(|p, data| Self::failable(Self::claim_status, p, data)),
+            )
+            .service(
+                web::resource("/custodial/register_signing_key")
+                    .to(|p, data| Self::failable(Self::register_signing_key, p, data)),
+            )
+            .service(
+                web::resource("/custodial/mint")
+                    .to(|p, data| Self::failable(Self::custodial_mint, p, data)),
+            )
             .service(
                 web::resource("/granted_tokens")
                     .to(|p, data| Self::failable(Self::tokens_for_user, p, data)),
@@ -197,10 +1876,32 @@ impl<DB: 'static + DatabaseAccess> ServiceProvider<DB> {
             .service(
                 web::resource("/subscribe").to(|p, data| Self::failable(Self::subscribe, p, data)),
             )
+            .service(
+                web::resource("/register_user")
+                    .to(|p, data| Self::failable(Self::register_user, p, data)),
+            )
+            .service(web::resource("/user").to(|p, data| Self::failable(Self::user, p, data)))
+            .service(
+                web::resource("/extend_subscription")
+                    .to(|p, data| Self::failable(Self::extend_subscription, p, data)),
+            )
+            .service(
+                web::resource("/migrate_subscription")
+                    .to(|p, data| Self::failable(Self::migrate_subscription, p, data)),
+            )
+            .service(
+                web::resource("/purchase_item")
+                    .to(|p, data| Self::failable(Self::purchase_item, p, data)),
+            )
+            .service(
+                web::resource("/entitlements")
+                    .to(|p, data| Self::failable(Self::entitlements, p, data)),
+            )
             .service(
                 web::resource("/related_communities")
                     .to(|p, data| Self::failable(Self::related_communities, p, data)),
             )
             .service(web::resource("/genesis_wallet_address").to(Self::genesis_wallet_address))
+            .service(web::resource("/health").to(Self::health))
     }
 }
@@ -1,6 +1,11 @@
-use crate::responses::ErrorResponse;
+use crate::{
+    database::{CommunityError, PurchaseError, SubscriptionError, UserError, WebhookError},
+    oracle::OracleError,
+    responses::ErrorResponse,
+};
 use actix_web::HttpResponse;
-use anyhow::Error;
+use anyhow::{Error, Result};
+use serde::Serialize;
 
 /// Converts the `anyhow::Result` into `HttpResponse`.
 /// Since not handled error is uncommon situation, we assume it to be an internal server error.
@@ -9,3 +14,35 @@ pub fn response_from_error(error: Error) -> HttpResponse {
     // TODO: Should we really return the error text to the user?
     HttpResponse::InternalServerError().json(ErrorResponse::error(&error.to_string()))
 }
+
+/// Converts the result of the Community Oracle request into `HttpResponse`.
+/// Requests rejected by the oracle are forwarded to user as `BadRequest`, and other errors
+/// are propagated to be handled as internal ones.
+pub fn response_from_oracle<T: Serialize>(result: Result<T, OracleError>) -> Result<HttpResponse> {
+    match result {
+        Ok(response) => Ok(HttpResponse::Ok().json(response)),
+        Err(OracleError::Rejected(error)) => Ok(HttpResponse::BadRequest().json(error)),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Converts the error of the database operation into `HttpResponse`.
+/// Community management, subscription, purchase, user registry and webhook outbox errors are caused by
+/// the incorrect request, so they are reported to user, and other errors are propagated to be handled as internal ones.
+pub fn response_from_db_error(error: Error) -> Result<HttpResponse> {
+    if error.downcast_ref::<SubscriptionError>().is_some()
+        || error.downcast_ref::<PurchaseError>().is_some()
+        || error.downcast_ref::<UserError>().is_some()
+        || error.downcast_ref::<WebhookError>().is_some()
+    {
+        return Ok(HttpResponse::BadRequest().json(ErrorResponse::error(&error.to_string())));
+    }
+
+    match error.downcast_ref::<CommunityError>() {
+        Some(CommunityError::VersionMismatch { .. }) => {
+            Ok(HttpResponse::Conflict().json(ErrorResponse::error(&error.to_string())))
+        }
+        Some(_) => Ok(HttpResponse::BadRequest().json(ErrorResponse::error(&error.to_string()))),
+        None => Err(error),
+    }
+}
@@ -7,25 +7,73 @@
 // rather than a full-fledged client experience.
 
 use crate::{
+    config::ResilienceConfig,
     database::Subscription,
-    zksync::{rest_client::RestApiClient, rpc_client::RpcClient},
+    resilience::{CircuitOpenError, DependencyError, DependencyStatus},
+    zksync::{
+        rest_client::{RestApiClient, TransactionsHistoryItem, HISTORY_PAGE_SIZE},
+        rpc_client::RpcClient,
+    },
 };
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
-use reqwest::Client;
+use num::BigUint;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use zksync_models::node::tx::{FranklinTx, Transfer, TransferFrom, TxEthSignature};
 
 mod rest_client;
 mod rpc_client;
 
 // Public re-exports and type declarations to not tie the rest application to the actual zkSync types.
-pub use zksync_models::node::Address;
+pub use zksync_models::node::{tx::PackedEthSignature, Address};
 
 // TODO: This should not be a hard-coded constant. However, if you see this line, the deadline has hit
 // us and we had no time to fix this issue. ¯\_(ツ)_/¯
 /// Cost of the subscription.
 pub const SUBSCRIPTION_COST: u64 = 100;
+/// Period covered by a single subscription payment.
+pub const SUBSCRIPTION_PERIOD_DAYS: i64 = 31;
+
+/// Errors of the burn transaction verification. Unlike other errors of this module, these are caused
+/// by the incorrect transaction provided by user rather than by the zkSync server.
+#[derive(Error, Debug)]
+pub enum BurnTxError {
+    #[error("Transaction is not found in the recent history of the account")]
+    NotFound,
+    #[error("Transaction is not executed yet")]
+    NotExecuted,
+    #[error("Transaction failed: {0}")]
+    Failed(String),
+    #[error("Transaction is not a correct burn transaction: {0}")]
+    Incorrect(String),
+}
+
+/// Outcome of the subscription check, see `ZksyncApp::check_subscription`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionStatus {
+    /// Subscription is paid for the current period.
+    Active,
+    /// Pre-signed tx paying for the period starting at the given time has just been sent.
+    Renewed(DateTime<Utc>),
+    /// Pre-signed tx paying for the period starting at the given time was rejected by zkSync
+    /// (e.g. subscription wallet lacks funds or its signing key was changed).
+    PaymentFailed {
+        period_start: DateTime<Utc>,
+        reason: String,
+    },
+    /// Subscription is not paid, and there is no pre-signed tx to pay for it.
+    Inactive,
+}
+
+impl SubscriptionStatus {
+    pub fn is_subscribed(&self) -> bool {
+        match self {
+            Self::Active | Self::Renewed(_) => true,
+            Self::PaymentFailed { .. } | Self::Inactive => false,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -42,6 +90,14 @@ pub trait SubscriptionTxExt {
 
     /// Returns the `DateTime` object showing when this transaction will not be valid anymore.
     fn valid_until(&self) -> DateTime<Utc>;
+
+    /// Returns `true` if both objects contain the same pair of signed transactions.
+    fn is_same_as(&self, other: &Self) -> bool;
+
+    /// Returns `true` if both transactions have the same validity window, i.e. pay for the same period.
+    fn has_same_window(&self, other: &Self) -> bool {
+        self.valid_from() == other.valid_from() && self.valid_until() == other.valid_until()
+    }
 }
 
 impl SubscriptionTxExt for SubscriptionTx {
@@ -56,11 +112,39 @@ impl SubscriptionTxExt for SubscriptionTx {
 
         DateTime::from_utc(time, Utc)
     }
+
+    fn is_same_as(&self, other: &Self) -> bool {
+        // Not every zkSync transaction type implements `PartialEq`, so the serialized forms are compared.
+        serde_json::to_value(self).ok() == serde_json::to_value(other).ok()
+    }
+}
+
+/// Checks that the message is signed by the owner of the Ethereum account. Signature is expected to be
+/// created according to the `personal_sign` rules (e.g. by `ethers.Signer.signMessage`).
+pub fn is_signed_by(message: &str, signature: &PackedEthSignature, address: Address) -> bool {
+    signature
+        .signature_recover_signer(message.as_bytes())
+        .map(|signer| signer == address)
+        .unwrap_or(false)
+}
+
+fn get_tx_type(tx: &serde_json::Value) -> Option<&str> {
+    tx.get("type").map(|value| value.as_str()).flatten()
+}
+
+/// Brings the transaction hash to the canonical form: zkSync reports hashes with the `sync-tx:` prefix,
+/// while users may provide them without it or with the `0x` prefix.
+fn normalize_tx_hash(tx_hash: &str) -> String {
+    tx_hash
+        .trim()
+        .to_lowercase()
+        .trim_start_matches("sync-tx:")
+        .trim_start_matches("0x")
+        .to_string()
 }
 
 #[derive(Debug)]
 pub struct ZksyncApp {
-    client: Client,
     rest_api_client: RestApiClient,
     rpc_client: RpcClient,
     burn_account_address: Address,
@@ -71,15 +155,20 @@ impl ZksyncApp {
         rest_api_addr: impl Into<String>,
         json_rpc_addr: impl Into<String>,
         burn_account_address: Address,
+        resilience: ResilienceConfig,
     ) -> Self {
         Self {
-            client: Client::new(),
-            rest_api_client: RestApiClient::new(rest_api_addr),
-            rpc_client: RpcClient::new(json_rpc_addr),
+            rest_api_client: RestApiClient::new(rest_api_addr, resilience.clone()),
+            rpc_client: RpcClient::new(json_rpc_addr, resilience),
             burn_account_address,
         }
     }
 
+    /// Returns the status of the zkSync server APIs.
+    pub fn status(&self) -> Vec<DependencyStatus> {
+        vec![self.rest_api_client.status(), self.rpc_client.status()]
+    }
+
     /// Checks whether user is currently subscribed to the community. Logic of this method
     /// can be described as follows:
     ///
@@ -90,7 +179,20 @@ impl ZksyncApp {
     ///   subscribed (if the transaction will fail, user will be "unsubscribed" as soon as it's executed by zkSync server)
     /// - Otherwise (no actual transaction in the account history, no new transaction to send), user is considered not
     ///   subscribed.
-    pub async fn is_user_subscribed(&self, subscription: Subscription) -> Result<bool> {
+    ///
+    /// If zkSync rejects the sent transaction, user is considered not subscribed as well. Errors which may be
+    /// caused by the zkSync server unavailability are reported as-is, since the transaction may be still accepted.
+    pub async fn check_subscription(
+        &self,
+        subscription: Subscription,
+    ) -> Result<SubscriptionStatus> {
+        // Time paid by the previous subscription wallets is honored regardless of the current wallet state.
+        if let Some(carried_over_until) = subscription.carried_over_until {
+            if Utc::now() <= carried_over_until {
+                return Ok(SubscriptionStatus::Active);
+            }
+        }
+
         match self
             .last_subscription_tx(subscription.subscription_wallet)
             .await?
@@ -105,7 +207,7 @@ impl ZksyncApp {
 
                 if subscribed {
                     // User is subscribed, no further actions required.
-                    return Ok(true);
+                    return Ok(SubscriptionStatus::Active);
                 }
             }
             None => {
@@ -125,13 +227,25 @@ impl ZksyncApp {
             // It may work incorrect if invoked rarely, thus it has to be replaced with a routine which schedules
             // sending txs right after the subscription has expired.
             // We may want to run a parallel thread which will invoke this method periodically for every subscription.
-            self.send_subscription_tx(new_sub_tx).await?;
-
-            return Ok(true);
+            let period_start = new_sub_tx.valid_from();
+
+            return match self.send_subscription_tx(new_sub_tx).await {
+                Ok(()) => Ok(SubscriptionStatus::Renewed(period_start)),
+                Err(error)
+                    if error.is_transient()
+                        || error.downcast_ref::<CircuitOpenError>().is_some() =>
+                {
+                    Err(error)
+                }
+                Err(error) => Ok(SubscriptionStatus::PaymentFailed {
+                    period_start,
+                    reason: error.to_string(),
+                }),
+            };
         }
 
         // User is not subscribed, and there is no actual tx to send for sub to be refreshed.
-        Ok(false)
+        Ok(SubscriptionStatus::Inactive)
     }
 
     /// Retrieves the subscription period for user in form of `(from, to)` tuple.
@@ -140,22 +254,18 @@ impl ZksyncApp {
         &self,
         subscription: Subscription,
     ) -> Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>)> {
-        // Subscription starts at the time of execution of the very first subscription tx.
+        // Subscription starts at the time of execution of the very first subscription tx
+        // (including the txs of the previous subscription wallets).
         // Here we assume that txs are executed at ~same time as the became valid.
         let started_at = subscription
-            .pre_signed_txs
+            .previous_wallets
             .iter()
+            .flat_map(|wallet| wallet.pre_signed_txs.iter())
+            .chain(subscription.pre_signed_txs.iter())
             .map(SubscriptionTxExt::valid_from)
             .min();
 
-        // To find the end of subscription period, we take the last subscription tx and
-        // add the length of the subscription period (31 days).
-        let expires_at = subscription
-            .pre_signed_txs
-            .iter()
-            .map(SubscriptionTxExt::valid_from)
-            .max()
-            .map(|date| date + Duration::days(31));
+        let expires_at = subscription.expires_at();
 
         Ok((started_at, expires_at))
     }
@@ -220,6 +330,160 @@ impl ZksyncApp {
         Ok(())
     }
 
+    /// Verifies that the new subscription txs continue the series of the already stored ones: every next tx
+    /// must use the subscription wallet nonces following the previous tx, and its validity window must start
+    /// once the window of the previous tx ends, exactly one subscription period after the previous one starts.
+    pub fn check_subscription_txs_series(
+        &self,
+        stored_txs: &[SubscriptionTx],
+        new_txs: &[SubscriptionTx],
+    ) -> Result<()> {
+        let mut new_txs: Vec<&SubscriptionTx> = new_txs.iter().collect();
+        new_txs.sort_by_key(|tx| tx.valid_from());
+
+        let mut previous = stored_txs.iter().max_by_key(|tx| tx.valid_from());
+        for tx in new_txs {
+            if let Some(previous) = previous {
+                let expected_nonce = previous.burn_tx.nonce + 1;
+                if tx.transfer_to_sub.to_nonce != expected_nonce {
+                    return Err(anyhow!(
+                        "`TransferFrom` nonce doesn't continue the series (expected {}, got {})",
+                        expected_nonce,
+                        tx.transfer_to_sub.to_nonce
+                    ));
+                }
+
+                if tx.valid_from() < previous.valid_until() {
+                    return Err(anyhow!(
+                        "Validity window of the tx overlaps with the previous one (starts at {}, previous ends at {})",
+                        tx.valid_from(),
+                        previous.valid_until()
+                    ));
+                }
+
+                // Every tx pays for a single period, so the windows can't be spread further apart.
+                let expected_start =
+                    previous.valid_from() + Duration::days(SUBSCRIPTION_PERIOD_DAYS);
+                if tx.valid_from() != expected_start {
+                    return Err(anyhow!(
+                        "Validity window of the tx must start one subscription period after the previous one (expected {}, got {})",
+                        expected_start,
+                        tx.valid_from()
+                    ));
+                }
+            }
+
+            previous = Some(tx);
+        }
+
+        Ok(())
+    }
+
+    /// Verifies that the tx can replace the stored tx for the same period: both must use the same
+    /// subscription wallet nonces, so the replacement doesn't break the series.
+    pub fn check_subscription_tx_replacement(
+        &self,
+        stored_tx: &SubscriptionTx,
+        new_tx: &SubscriptionTx,
+    ) -> Result<()> {
+        if new_tx.transfer_to_sub.to_nonce != stored_tx.transfer_to_sub.to_nonce
+            || new_tx.burn_tx.nonce != stored_tx.burn_tx.nonce
+        {
+            return Err(anyhow!(
+                "Replacement tx must use the same nonces as the replaced one (expected {}/{}, got {}/{})",
+                stored_tx.transfer_to_sub.to_nonce,
+                stored_tx.burn_tx.nonce,
+                new_tx.transfer_to_sub.to_nonce,
+                new_tx.burn_tx.nonce
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the time until which the subscription is paid by the already sent transactions
+    /// (including the time carried over from the previous subscription wallets).
+    pub async fn paid_until(&self, subscription: &Subscription) -> Result<Option<DateTime<Utc>>> {
+        let paid_by_wallet = self
+            .last_subscription_tx(subscription.subscription_wallet)
+            .await?
+            .map(|datetime| datetime + Duration::days(SUBSCRIPTION_PERIOD_DAYS));
+
+        Ok(paid_by_wallet.max(subscription.carried_over_until))
+    }
+
+    /// Verifies that the transaction with the provided hash is a successfully executed transfer of
+    /// `amount` tokens (in the base units) from the user to the burn account. Returns the canonical form of the transaction hash.
+    pub async fn check_burn_tx(
+        &self,
+        user: Address,
+        tx_hash: &str,
+        token_id: u16,
+        amount: &BigUint,
+    ) -> Result<String> {
+        let tx_hash = normalize_tx_hash(tx_hash);
+
+        let tx = self
+            .find_account_tx(user, &tx_hash)
+            .await?
+            .ok_or(BurnTxError::NotFound)?;
+
+        match tx.success {
+            Some(true) => {}
+            Some(false) => {
+                return Err(BurnTxError::Failed(tx.fail_reason.unwrap_or_default()).into());
+            }
+            None => return Err(BurnTxError::NotExecuted.into()),
+        }
+
+        if get_tx_type(&tx.tx) != Some("Transfer") {
+            return Err(BurnTxError::Incorrect("transaction is not a transfer".into()).into());
+        }
+        let transfer: Transfer = serde_json::from_value(tx.tx)
+            .map_err(|err| anyhow!("zkSync provided incorrect transaction: {}", err))?;
+
+        if transfer.from != user {
+            return Err(BurnTxError::Incorrect(format!(
+                "sender is incorrect (expected {}, got {})",
+                user, transfer.from
+            ))
+            .into());
+        }
+
+        if transfer.to != self.burn_account_address {
+            return Err(BurnTxError::Incorrect(format!(
+                "recipient is not the burn account (expected {}, got {})",
+                self.burn_account_address, transfer.to
+            ))
+            .into());
+        }
+
+        if transfer.token != token_id {
+            return Err(BurnTxError::Incorrect(format!(
+                "token is incorrect (expected {}, got {})",
+                token_id, transfer.token
+            ))
+            .into());
+        }
+
+        if transfer.amount != *amount {
+            return Err(BurnTxError::Incorrect(format!(
+                "amount is incorrect (expected {}, got {})",
+                amount, transfer.amount
+            ))
+            .into());
+        }
+
+        Ok(tx_hash)
+    }
+
+    /// Returns the committed balance of the account in the token with the given symbol.
+    pub async fn token_balance(&self, address: Address, token_symbol: &str) -> Result<BigUint> {
+        let balances = self.rpc_client.account_balances(address).await?;
+
+        Ok(balances.get(token_symbol).cloned().unwrap_or_default())
+    }
+
     /// Sends the provided transaction to the zkSync network.
     pub async fn send_subscription_tx(&self, subscription_tx: &SubscriptionTx) -> Result<()> {
         let subscription_tx = subscription_tx.clone();
@@ -243,16 +507,38 @@ impl ZksyncApp {
         Ok(())
     }
 
+    /// Looks for the transaction with the provided (normalized) hash in the account history,
+    /// going from the newest transactions to the oldest ones page by page.
+    async fn find_account_tx(
+        &self,
+        address: Address,
+        tx_hash: &str,
+    ) -> Result<Option<TransactionsHistoryItem>> {
+        let mut offset = 0;
+        loop {
+            let page = self
+                .rest_api_client
+                .get_transactions_history_page(address, offset, HISTORY_PAGE_SIZE)
+                .await?;
+            let page_size = page.len() as u64;
+
+            let tx = page
+                .into_iter()
+                .find(|tx| tx.hash.as_deref().map(normalize_tx_hash).as_deref() == Some(tx_hash));
+            if tx.is_some() || page_size < HISTORY_PAGE_SIZE {
+                return Ok(tx);
+            }
+
+            offset += page_size;
+        }
+    }
+
     /// Attempts to retrieve timestamp of the last subscription transaction executed on
     /// the provided subscription wallet.
     pub async fn last_subscription_tx(
         &self,
         subscription_address: Address,
     ) -> Result<Option<DateTime<Utc>>> {
-        fn get_tx_type(tx: &serde_json::Value) -> Option<&str> {
-            tx.get("type").map(|value| value.as_str()).flatten()
-        }
-
         // Note: Here we must check not only for the executed txs, but for pending as well.
         // However, if the latest tx was executed and failed, it must not be taken into account.
         // We should the latest of either pending or successfully executed tx for a wallet.
@@ -361,6 +647,6 @@ impl ZksyncApp {
     /// than 31 days.
     fn check_subscription_status(&self, last_tx_timestamp: DateTime<Utc>) -> bool {
         let current_time = Utc::now();
-        current_time <= last_tx_timestamp + Duration::days(31)
+        current_time <= last_tx_timestamp + Duration::days(SUBSCRIPTION_PERIOD_DAYS)
     }
 }
@@ -1,13 +1,21 @@
 //! REST API client for the zkSync server.
 
 // Built-in imports
+use std::sync::Arc;
 // External uses
 use anyhow::{anyhow, Result};
 use chrono::NaiveDateTime;
 use serde_derive::{Deserialize, Serialize};
 // Workspace uses
 // Local uses
-use crate::zksync::Address;
+use crate::{
+    config::ResilienceConfig,
+    resilience::{Dependency, DependencyStatus},
+    zksync::Address,
+};
+
+/// Maximum amount of the history items returned by the zkSync server per request.
+pub const HISTORY_PAGE_SIZE: u64 = 100;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct TransactionsHistoryItem {
@@ -23,49 +31,79 @@ pub struct TransactionsHistoryItem {
     pub created_at: NaiveDateTime,
 }
 
-/// `RpcClient` is capable of interacting with the ZKSync node via its
-/// JSON RPC interface.
+/// `RestApiClient` is capable of interacting with the ZKSync node via its
+/// REST API interface.
 #[derive(Debug, Clone)]
 pub struct RestApiClient {
     api_addr: String,
     client: reqwest::Client,
+    dependency: Arc<Dependency>,
 }
 
 impl RestApiClient {
     /// Creates a new `RestApiClient` object.
-    pub fn new(api_addr: impl Into<String>) -> Self {
+    pub fn new(api_addr: impl Into<String>, resilience: ResilienceConfig) -> Self {
+        let dependency = Dependency::new("zksync_rest_api", resilience);
+
         Self {
             api_addr: api_addr.into(),
-            client: reqwest::Client::new(),
+            client: dependency.http_client(),
+            dependency: Arc::new(dependency),
         }
     }
 
+    pub fn status(&self) -> DependencyStatus {
+        self.dependency.status()
+    }
+
+    /// Returns the latest transactions of the account, from newest to oldest.
     pub async fn get_transactions_history(
         &self,
         address: Address,
+    ) -> Result<Vec<TransactionsHistoryItem>> {
+        self.get_transactions_history_page(address, 0, HISTORY_PAGE_SIZE)
+            .await
+    }
+
+    /// Returns `limit` transactions of the account, skipping `offset` newest ones.
+    pub async fn get_transactions_history_page(
+        &self,
+        address: Address,
+        offset: u64,
+        limit: u64,
     ) -> Result<Vec<TransactionsHistoryItem>> {
         let address = format!("0x{}", hex::encode(address.as_ref()));
 
         let formatted_postfix = format!(
             "/account/{address}/history/{offset}/{limit}",
             address = address,
-            offset = 0,
-            limit = 40
+            offset = offset,
+            limit = limit
         );
         let endpoint = self.endpoint(&formatted_postfix);
 
-        let response = self.client.get(&endpoint).send().await?;
+        // Reading the history has no side effects, so the request can be safely retried.
+        self.dependency
+            .call(true, || async {
+                let response = self
+                    .client
+                    .get(&endpoint)
+                    .send()
+                    .await?
+                    .error_for_status()?;
 
-        let json_data: Vec<TransactionsHistoryItem> = match response.json().await {
-            Ok(json) => json,
-            Err(error) => {
-                log::error!("zkSync server returned incorrect JSON: {}", error);
-                log::error!("request path: {}", &endpoint);
-                return Err(anyhow!("Unable to decode response from the zkSync server",));
-            }
-        };
+                let json_data: Vec<TransactionsHistoryItem> = match response.json().await {
+                    Ok(json) => json,
+                    Err(error) => {
+                        log::error!("zkSync server returned incorrect JSON: {}", error);
+                        log::error!("request path: {}", &endpoint);
+                        return Err(anyhow!("Unable to decode response from the zkSync server",));
+                    }
+                };
 
-        Ok(json_data)
+                Ok(json_data)
+            })
+            .await
     }
 
     fn endpoint(&self, postfix: &str) -> String {
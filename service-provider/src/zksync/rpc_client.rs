@@ -1,14 +1,36 @@
 //! RPC client for the zkSync server.
 
 // Built-in imports
+use std::{collections::HashMap, sync::Arc};
 // External uses
 use anyhow::{anyhow, Result};
 use jsonrpc_core::types::response::Output;
+use num::BigUint;
+use serde_derive::Deserialize;
 // Workspace uses
-use zksync_models::node::tx::{FranklinTx, PackedEthSignature, TxHash};
+use zksync_models::node::{
+    tx::{FranklinTx, PackedEthSignature, TxHash},
+    Address,
+};
 // use server::api_server::rpc_server::AccountInfoResp;
 // Local uses
 use self::messages::JsonRpcRequest;
+use crate::{
+    config::ResilienceConfig,
+    resilience::{Dependency, DependencyStatus},
+};
+
+/// Subset of the account state returned by the `account_info` method.
+#[derive(Debug, Deserialize)]
+struct AccountState {
+    /// Balances keyed by the token symbol, amounts are encoded as decimal strings.
+    balances: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountInfoResp {
+    committed: AccountState,
+}
 
 /// State of the ZKSync operation.
 #[derive(Debug)]
@@ -23,29 +45,59 @@ pub struct OperationState {
 pub struct RpcClient {
     rpc_addr: String,
     client: reqwest::Client,
+    dependency: Arc<Dependency>,
 }
 
 impl RpcClient {
     /// Creates a new `RpcClient` object.
-    pub fn new(rpc_addr: impl Into<String>) -> Self {
+    pub fn new(rpc_addr: impl Into<String>, resilience: ResilienceConfig) -> Self {
+        let dependency = Dependency::new("zksync_json_rpc", resilience);
+
         Self {
             rpc_addr: rpc_addr.into(),
-            client: reqwest::Client::new(),
+            client: dependency.http_client(),
+            dependency: Arc::new(dependency),
         }
     }
 
+    pub fn status(&self) -> DependencyStatus {
+        self.dependency.status()
+    }
+
     pub async fn send_txs_batch(
         &self,
         txs: Vec<(FranklinTx, Option<PackedEthSignature>)>,
     ) -> Result<Vec<TxHash>> {
         let msg = JsonRpcRequest::submit_txs_batch(txs);
 
-        let ret = self.post(&msg).await?;
+        // Sending transactions is not idempotent, so the request is never retried.
+        let ret = self.dependency.call(false, || self.post(&msg)).await?;
         let tx_hashes =
             serde_json::from_value(ret).expect("failed to parse `send_txs_batch` response");
         Ok(tx_hashes)
     }
 
+    /// Returns the committed balances of the account, keyed by the token symbol.
+    pub async fn account_balances(&self, address: Address) -> Result<HashMap<String, BigUint>> {
+        let msg = JsonRpcRequest::account_info(address);
+
+        let ret = self.dependency.call(true, || self.post(&msg)).await?;
+        let account_info: AccountInfoResp = serde_json::from_value(ret)
+            .map_err(|err| anyhow!("failed to parse `account_info` response: {}", err))?;
+
+        account_info
+            .committed
+            .balances
+            .into_iter()
+            .map(|(token, amount)| -> Result<(String, BigUint)> {
+                let amount = amount
+                    .parse()
+                    .map_err(|err| anyhow!("incorrect {} balance {}: {}", token, amount, err))?;
+                Ok((token, amount))
+            })
+            .collect()
+    }
+
     /// Performs a POST query to the JSON RPC endpoint,
     /// and decodes the response, returning the decoded `serde_json::Value`.
     /// `Ok` is returned only for successful calls, for any kind of error
@@ -73,14 +125,9 @@ impl RpcClient {
             .post(&self.rpc_addr)
             .json(&message)
             .send()
-            .await?;
-        if res.status() != reqwest::StatusCode::OK {
-            anyhow!(
-                "Post query responded with a non-OK response: {}",
-                res.status()
-            );
-        }
-        let reply: Output = res.json().await.unwrap();
+            .await?
+            .error_for_status()?;
+        let reply: Output = res.json().await?;
 
         Ok(reply)
     }
@@ -89,7 +136,10 @@ impl RpcClient {
 /// Structures representing the RPC request messages.
 mod messages {
     use serde_derive::Serialize;
-    use zksync_models::node::tx::{FranklinTx, PackedEthSignature, TxEthSignature};
+    use zksync_models::node::{
+        tx::{FranklinTx, PackedEthSignature, TxEthSignature},
+        Address,
+    };
 
     #[derive(Debug, Serialize)]
     pub struct JsonRpcRequest {
@@ -129,5 +179,11 @@ mod messages {
             params.push(serde_json::to_value(txs).expect("serialization fail"));
             Self::create("submit_txs_batch", params)
         }
+
+        pub fn account_info(address: Address) -> Self {
+            let mut params = Vec::new();
+            params.push(serde_json::to_value(address).expect("serialization fail"));
+            Self::create("account_info", params)
+        }
     }
 }